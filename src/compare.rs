@@ -0,0 +1,147 @@
+/// Number of identical lines shown before the first differing line
+const CONTEXT_LINES: usize = 3;
+
+/// Names of the expected and the actual side of a comparison
+pub type Labels = (&'static str, &'static str);
+
+/// The C build is the reference the Rust build must match
+pub const C_VS_RUST: Labels = ("C", "Rust");
+
+/// Exit code and captured output of one side of a differential run
+#[derive(Debug)]
+pub struct SideOutput {
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// A difference between the expected and the actual run
+#[derive(Debug, PartialEq, Eq)]
+pub enum Divergence {
+    ExitCode { expected: i32, actual: i32 },
+    /// First line at which a stream differs; `None` means the output ended there
+    Output {
+        stream: &'static str,
+        /// 1-based line number
+        line: usize,
+        expected: Option<String>,
+        actual: Option<String>,
+        /// Identical lines just before the difference
+        context: Vec<String>,
+    },
+}
+
+impl Divergence {
+    /// One-line description, used in the error message
+    pub fn summary(&self, labels: Labels) -> String {
+        match self {
+            Divergence::ExitCode { expected, actual } => {
+                format!("exit code {} ({}) vs {} ({})", expected, labels.0, actual, labels.1)
+            }
+            Divergence::Output { stream, line, .. } => format!("{} differs at line {}", stream, line),
+        }
+    }
+
+    /// Print the divergence with its context lines
+    pub fn print(&self, labels: Labels) {
+        match self {
+            Divergence::ExitCode { .. } => println!("Divergence: {}", self.summary(labels)),
+            Divergence::Output { line, expected, actual, context, .. } => {
+                println!("Divergence: {}:", self.summary(labels));
+                let first_context = line - context.len();
+                for (i, text) in context.iter().enumerate() {
+                    println!("         {:>5} | {}", first_context + i, text);
+                }
+                let shown = |text: &Option<String>| text.clone().unwrap_or_else(|| "<end of output>".to_string());
+                println!("  {:<6} {:>5} | {}", labels.0, line, shown(expected));
+                println!("  {:<6} {:>5} | {}", labels.1, line, shown(actual));
+            }
+        }
+    }
+}
+
+/// Compare two runs: exit code, then stdout, then stderr.
+/// Returns every aspect that differs; an empty list means the runs are equivalent.
+pub fn compare(expected: &SideOutput, actual: &SideOutput) -> Vec<Divergence> {
+    let mut divergences = Vec::new();
+    if expected.exit_code != actual.exit_code {
+        divergences.push(Divergence::ExitCode { expected: expected.exit_code, actual: actual.exit_code });
+    }
+    divergences.extend(first_difference("stdout", &expected.stdout, &actual.stdout));
+    divergences.extend(first_difference("stderr", &expected.stderr, &actual.stderr));
+    divergences
+}
+
+/// The first line at which two outputs differ, with the lines leading up to it
+pub fn first_difference(stream: &'static str, expected: &str, actual: &str) -> Option<Divergence> {
+    if expected == actual {
+        return None;
+    }
+    let expected_lines: Vec<&str> = expected.lines().collect();
+    let actual_lines: Vec<&str> = actual.lines().collect();
+
+    let index = expected_lines
+        .iter()
+        .zip(&actual_lines)
+        .position(|(expected, actual)| expected != actual)
+        // One output is a prefix of the other, or they differ only in a
+        // trailing newline, which `lines` does not report
+        .unwrap_or_else(|| expected_lines.len().min(actual_lines.len()));
+
+    let context_start = index.saturating_sub(CONTEXT_LINES);
+    Some(Divergence::Output {
+        stream,
+        line: index + 1,
+        expected: expected_lines.get(index).map(|line| line.to_string()),
+        actual: actual_lines.get(index).map(|line| line.to_string()),
+        context: expected_lines[context_start..index].iter().map(|line| line.to_string()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn side(exit_code: i32, stdout: &str, stderr: &str) -> SideOutput {
+        SideOutput {
+            exit_code,
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_compare_identical() {
+        assert!(compare(&side(1, "a\nb\n", "e\n"), &side(1, "a\nb\n", "e\n")).is_empty());
+    }
+
+    #[test]
+    fn test_compare_reports_each_aspect() {
+        let divergences = compare(&side(0, "a\nb\n", "warn\n"), &side(139, "a\nb\n", ""));
+        assert_eq!(divergences.len(), 2);
+        assert_eq!(divergences[0], Divergence::ExitCode { expected: 0, actual: 139 });
+        assert_eq!(divergences[0].summary(C_VS_RUST), "exit code 0 (C) vs 139 (Rust)");
+        assert_eq!(divergences[1].summary(C_VS_RUST), "stderr differs at line 1");
+    }
+
+    #[test]
+    fn test_first_difference_context() {
+        let c = "1\n2\n3\n4\n5\nsix\n7\n";
+        let rust = "1\n2\n3\n4\n5\n6\n7\n";
+        let divergence = first_difference("stdout", c, rust).unwrap();
+        assert_eq!(
+            divergence,
+            Divergence::Output {
+                stream: "stdout",
+                line: 6,
+                expected: Some("six".to_string()),
+                actual: Some("6".to_string()),
+                context: vec!["3".to_string(), "4".to_string(), "5".to_string()],
+            }
+        );
+
+        // Output that stops early diverges where it ends
+        let divergence = first_difference("stdout", "1\n2\n", "1\n2\n3\n").unwrap();
+        assert!(matches!(divergence, Divergence::Output { line: 3, expected: None, .. }));
+    }
+}
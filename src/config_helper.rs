@@ -1,6 +1,7 @@
 use crate::error::{Error, Result};
 use std::path::Path;
 use std::process::Command;
+use std::time::Duration;
 
 /// Get the c2rust-config binary path from environment or use default
 fn get_c2rust_config_path() -> String {
@@ -19,22 +20,82 @@ pub fn check_c2rust_config_exists() -> Result<()> {
         .ok_or(Error::ConfigToolNotFound)
 }
 
-/// Save test configuration using c2rust-config
-pub fn save_config(dir: &str, command: &str, feature: Option<&str>, project_root: &Path) -> Result<()> {
+/// Test settings recorded in the c2rust config for a feature
+pub struct TestConfig {
+    /// Test directory, relative to the project root
+    pub dir: String,
+    /// Test command and its arguments, exactly as given on the command line
+    pub argv: Vec<String>,
+    /// Optional time limit for the test command
+    pub timeout: Option<Duration>,
+    /// Extra environment variables for the test command
+    pub env: Vec<(String, String)>,
+    /// Interpreter that runs the command as a shell script, if shell mode is on
+    pub shell: Option<String>,
+    /// Result of the run that is saving this configuration
+    pub last_run: Option<LastRun>,
+}
+
+/// Outcome of the run that recorded a configuration
+pub struct LastRun {
+    pub passed: bool,
+    /// Exit code c2rust-test reported for the run
+    pub exit_code: i32,
+}
+
+impl TestConfig {
+    /// Key/value pairs written to the c2rust config.
+    /// Optional settings are written as empty strings so that stale values from
+    /// an earlier run are cleared.
+    ///
+    /// The command is stored twice: test.cmd holds a POSIX shell-quoted string
+    /// for people to read, test.argv a JSON array that replays use verbatim.
+    fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![
+            ("test.dir", self.dir.clone()),
+            ("test.cmd", shell_words::join(&self.argv)),
+            ("test.argv", serde_json::to_string(&self.argv).unwrap_or_default()),
+            ("test.timeout", self.timeout.map(format_duration).unwrap_or_default()),
+            ("test.env", format_env(&self.env)),
+            ("test.shell", if self.shell.is_some() { "true" } else { "false" }.to_string()),
+            ("test.shell_path", self.shell.clone().unwrap_or_default()),
+        ];
+        if let Some(last_run) = &self.last_run {
+            entries.push(("test.last_status", if last_run.passed { "pass" } else { "fail" }.to_string()));
+            entries.push(("test.last_exit_code", last_run.exit_code.to_string()));
+        }
+        entries
+    }
+}
+
+/// The c2rust-config invocations (full argv) that `save_config` runs, in order
+pub fn save_invocations(config: &TestConfig, feature: Option<&str>) -> Vec<Vec<String>> {
     let config_path = get_c2rust_config_path();
     let feature_args: Vec<&str> = feature.map(|f| vec!["--feature", f]).unwrap_or_default();
 
-    // Save both test.dir and test.cmd
-    for (key, value) in [("test.dir", dir), ("test.cmd", command)] {
-        let output = Command::new(&config_path)
-            .args(&["config", "--make"])
-            .args(&feature_args)
-            .args(&["--set", key, value])
+    config
+        .entries()
+        .into_iter()
+        .map(|(key, value)| {
+            let mut argv = vec![config_path.clone(), "config".to_string(), "--make".to_string()];
+            argv.extend(feature_args.iter().map(|arg| arg.to_string()));
+            argv.extend(["--set".to_string(), key.to_string(), value]);
+            argv
+        })
+        .collect()
+}
+
+/// Save test configuration using c2rust-config
+pub fn save_config(config: &TestConfig, feature: Option<&str>, project_root: &Path) -> Result<()> {
+    for argv in save_invocations(config, feature) {
+        let output = Command::new(&argv[0])
+            .args(&argv[1..])
             .current_dir(project_root)
             .output()
             .map_err(|e| Error::ConfigSaveFailed(format!("Failed to execute c2rust-config: {}", e)))?;
 
         if !output.status.success() {
+            let key = &argv[argv.len() - 2];
             let stderr = String::from_utf8_lossy(&output.stderr);
             return Err(Error::ConfigSaveFailed(format!("Failed to save {}: {}", key, stderr)));
         }
@@ -43,6 +104,176 @@ pub fn save_config(dir: &str, command: &str, feature: Option<&str>, project_root
     Ok(())
 }
 
+/// Load the test configuration previously saved for a feature
+pub fn load_config(feature: Option<&str>, project_root: &Path) -> Result<TestConfig> {
+    let require = |key: &str| {
+        get_config_value(key, feature, project_root)?
+            .ok_or_else(|| Error::ConfigLoadFailed(format!("{} is not set; run 'c2rust-test test' first", key)))
+    };
+
+    let dir = require("test.dir")?;
+    // Configurations saved before test.argv existed only have test.cmd
+    let argv = match get_config_value("test.argv", feature, project_root)? {
+        Some(value) => serde_json::from_str::<Vec<String>>(&value)
+            .map_err(|e| Error::ConfigLoadFailed(format!("test.argv: expected a JSON array of strings: {}", e)))?,
+        None => shell_words::split(&require("test.cmd")?)
+            .map_err(|e| Error::ConfigLoadFailed(format!("test.cmd: {}", e)))?,
+    };
+    if argv.is_empty() {
+        return Err(Error::ConfigLoadFailed("test.cmd is empty".to_string()));
+    }
+    let timeout = get_config_value("test.timeout", feature, project_root)?
+        .map(|value| parse_duration(&value))
+        .transpose()
+        .map_err(|e| Error::ConfigLoadFailed(format!("test.timeout: {}", e)))?;
+    let env = get_config_value("test.env", feature, project_root)?
+        .map(|value| parse_env(&value))
+        .transpose()
+        .map_err(|e| Error::ConfigLoadFailed(format!("test.env: {}", e)))?
+        .unwrap_or_default();
+
+    // The recorded interpreter is reused so replays behave like the original run
+    let shell = match get_config_value("test.shell", feature, project_root)?.as_deref() {
+        Some("true") => Some(
+            get_config_value("test.shell_path", feature, project_root)?
+                .unwrap_or_else(crate::executor::default_shell),
+        ),
+        _ => None,
+    };
+
+    if shell.is_some() {
+        crate::executor::shell_script(&argv).map_err(|e| Error::ConfigLoadFailed(format!("test.argv: {}", e)))?;
+    }
+
+    Ok(TestConfig { dir, argv, timeout, env, shell, last_run: None })
+}
+
+/// Overrides for one side of a `diff` run, read from `test.<side>.dir` and
+/// `test.<side>.env` (e.g. `test.c.dir`, `test.rust.env`)
+#[derive(Debug, Default, PartialEq)]
+pub struct SideOverrides {
+    /// Test directory relative to the project root, replacing test.dir
+    pub dir: Option<String>,
+    /// Variables added to test.env, replacing any with the same name
+    pub env: Vec<(String, String)>,
+}
+
+impl SideOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.dir.is_none() && self.env.is_empty()
+    }
+}
+
+/// Load the `diff` overrides of `side` ("c" or "rust")
+pub fn load_side_overrides(side: &str, feature: Option<&str>, project_root: &Path) -> Result<SideOverrides> {
+    let dir_key = format!("test.{}.dir", side);
+    let env_key = format!("test.{}.env", side);
+    let dir = get_config_value(&dir_key, feature, project_root)?;
+    let env = get_config_value(&env_key, feature, project_root)?
+        .map(|value| parse_env(&value))
+        .transpose()
+        .map_err(|e| Error::ConfigLoadFailed(format!("{}: {}", env_key, e)))?
+        .unwrap_or_default();
+    Ok(SideOverrides { dir, env })
+}
+
+impl TestConfig {
+    /// This configuration as run for one side of a `diff`
+    pub fn with_overrides(&self, overrides: &SideOverrides) -> TestConfig {
+        let mut env: Vec<(String, String)> = self
+            .env
+            .iter()
+            .filter(|(key, _)| !overrides.env.iter().any(|(override_key, _)| override_key == key))
+            .cloned()
+            .collect();
+        env.extend(overrides.env.iter().cloned());
+
+        TestConfig {
+            dir: overrides.dir.clone().unwrap_or_else(|| self.dir.clone()),
+            argv: self.argv.clone(),
+            timeout: self.timeout,
+            env,
+            shell: self.shell.clone(),
+            last_run: None,
+        }
+    }
+}
+
+/// Read a single value from the c2rust config.
+/// Returns `Ok(None)` if the key is not set or is empty.
+pub fn get_config_value(key: &str, feature: Option<&str>, project_root: &Path) -> Result<Option<String>> {
+    let config_path = get_c2rust_config_path();
+    let feature_args: Vec<&str> = feature.map(|f| vec!["--feature", f]).unwrap_or_default();
+
+    let output = Command::new(&config_path)
+        .args(["config", "--make"])
+        .args(&feature_args)
+        .args(["--get", key])
+        .current_dir(project_root)
+        .output()
+        .map_err(|e| Error::ConfigLoadFailed(format!("Failed to execute c2rust-config: {}", e)))?;
+
+    // c2rust-config exits with an error for keys that have never been set
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    let value = String::from_utf8_lossy(&output.stdout).trim_end_matches(['\r', '\n']).to_string();
+    Ok(Some(value).filter(|v| !v.is_empty()))
+}
+
+/// Serialize environment variables as a JSON object, or "" if there are none
+fn format_env(env: &[(String, String)]) -> String {
+    if env.is_empty() {
+        return String::new();
+    }
+    let map: serde_json::Map<String, serde_json::Value> = env
+        .iter()
+        .map(|(key, value)| (key.clone(), serde_json::Value::String(value.clone())))
+        .collect();
+    serde_json::Value::Object(map).to_string()
+}
+
+/// Parse the JSON object stored in test.env
+fn parse_env(value: &str) -> std::result::Result<Vec<(String, String)>, String> {
+    let map: std::collections::BTreeMap<String, String> =
+        serde_json::from_str(value).map_err(|e| format!("expected a JSON object of strings: {}", e))?;
+    Ok(map.into_iter().collect())
+}
+
+/// Parse a duration such as `90`, `90s`, `1.5m`, `2h` or `500ms`.
+/// A bare number is interpreted as seconds.
+pub fn parse_duration(value: &str) -> std::result::Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", value))?;
+    let multiplier = match unit.trim() {
+        "" | "s" => 1.0,
+        "ms" => 0.001,
+        "m" => 60.0,
+        "h" => 3600.0,
+        other => return Err(format!("invalid duration unit '{}' in '{}' (use ms, s, m or h)", other, value)),
+    };
+
+    Duration::try_from_secs_f64(number * multiplier)
+        .map_err(|_| format!("invalid duration '{}'", value))
+}
+
+/// Format a duration so that `parse_duration` reads it back unchanged
+pub fn format_duration(duration: Duration) -> String {
+    if duration.subsec_millis() == 0 {
+        format!("{}s", duration.as_secs())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,4 +322,114 @@ mod tests {
             std::env::set_var("C2RUST_CONFIG", val);
         }
     }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("1.5m").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("ten").is_err());
+        assert!(parse_duration("5d").is_err());
+    }
+
+    #[test]
+    fn test_format_duration_round_trip() {
+        for duration in [Duration::from_secs(90), Duration::from_millis(1500)] {
+            assert_eq!(parse_duration(&format_duration(duration)).unwrap(), duration);
+        }
+    }
+
+    #[test]
+    fn test_env_round_trip() {
+        let env = vec![
+            ("CFLAGS".to_string(), "-O0 -g".to_string()),
+            ("VERBOSE".to_string(), "1".to_string()),
+        ];
+        let stored = format_env(&env);
+        assert_eq!(stored, r#"{"CFLAGS":"-O0 -g","VERBOSE":"1"}"#);
+        assert_eq!(parse_env(&stored).unwrap(), env);
+        assert_eq!(format_env(&[]), "");
+        assert!(parse_env("VERBOSE=1").is_err());
+    }
+
+    #[test]
+    fn test_entries_store_argv_losslessly() {
+        let config = TestConfig {
+            dir: ".".to_string(),
+            argv: vec!["./t.sh".to_string(), "a b".to_string(), "it's".to_string()],
+            timeout: None,
+            env: Vec::new(),
+            shell: None,
+            last_run: None,
+        };
+        let entries = config.entries();
+        let value = |key: &str| entries.iter().find(|(k, _)| *k == key).unwrap().1.clone();
+
+        assert_eq!(value("test.cmd"), r#"./t.sh 'a b' 'it'\''s'"#);
+        assert_eq!(shell_words::split(&value("test.cmd")).unwrap(), config.argv);
+        assert_eq!(value("test.argv"), r#"["./t.sh","a b","it's"]"#);
+    }
+
+    #[test]
+    fn test_with_overrides() {
+        let config = TestConfig {
+            dir: "build".to_string(),
+            argv: vec!["./run_tests".to_string()],
+            timeout: None,
+            env: vec![("MODE".to_string(), "c".to_string()), ("VERBOSE".to_string(), "1".to_string())],
+            shell: None,
+            last_run: None,
+        };
+        assert!(SideOverrides::default().is_empty());
+
+        let rust = config.with_overrides(&SideOverrides {
+            dir: Some("build-rust".to_string()),
+            env: vec![("MODE".to_string(), "rust".to_string())],
+        });
+        assert_eq!(rust.dir, "build-rust");
+        assert_eq!(rust.argv, config.argv);
+        assert_eq!(
+            rust.env,
+            vec![("VERBOSE".to_string(), "1".to_string()), ("MODE".to_string(), "rust".to_string())]
+        );
+        assert_eq!(config.with_overrides(&SideOverrides::default()).dir, "build");
+    }
+
+    #[test]
+    #[serial]
+    fn test_save_invocations() {
+        let original = std::env::var("C2RUST_CONFIG").ok();
+        std::env::remove_var("C2RUST_CONFIG");
+
+        let config = TestConfig {
+            dir: "build".to_string(),
+            argv: vec!["ctest".to_string()],
+            timeout: Some(Duration::from_secs(60)),
+            env: Vec::new(),
+            shell: None,
+            last_run: None,
+        };
+        let invocations = save_invocations(&config, Some("f1"));
+        assert_eq!(
+            invocations[0],
+            vec!["c2rust-config", "config", "--make", "--feature", "f1", "--set", "test.dir", "build"]
+        );
+        assert!(invocations.iter().any(|argv| argv[6] == "test.timeout" && argv[7] == "60s"));
+        assert!(!invocations.iter().any(|argv| argv[6] == "test.last_status"));
+
+        let failed = TestConfig {
+            last_run: Some(LastRun { passed: false, exit_code: 139 }),
+            ..config
+        };
+        let invocations = save_invocations(&failed, Some("f1"));
+        assert!(invocations.iter().any(|argv| argv[6] == "test.last_status" && argv[7] == "fail"));
+        assert!(invocations.iter().any(|argv| argv[6] == "test.last_exit_code" && argv[7] == "139"));
+
+        if let Some(val) = original {
+            std::env::set_var("C2RUST_CONFIG", val);
+        }
+    }
 }
@@ -0,0 +1,247 @@
+use crate::junit;
+use crate::results::{self, TestCase, TestStatus};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Location of ctest's log of the most recent run, relative to the build directory
+const LAST_TEST_LOG: &str = "Testing/Temporary/LastTest.log";
+
+/// Whether the test command invokes ctest directly
+pub fn is_ctest_command(test_cmd: &[String]) -> bool {
+    test_cmd
+        .first()
+        .and_then(|program| Path::new(program).file_name())
+        .is_some_and(|name| name == "ctest")
+}
+
+/// Collect the results of the ctest run that just finished in `test_dir`.
+///
+/// If the command asked ctest for JUnit output (`--output-junit`), that file is
+/// parsed; otherwise `Testing/Temporary/LastTest.log` is read. Results written
+/// before `since` belong to an earlier run and are ignored. Returns `None` with
+/// a warning when no results are available.
+pub fn collect(test_cmd: &[String], test_dir: &Path, since: SystemTime) -> Option<Vec<TestCase>> {
+    // ctest resolves its output paths against the build directory
+    let build_dir = match option_value(test_cmd, "--test-dir") {
+        Some(dir) => test_dir.join(dir),
+        None => test_dir.to_path_buf(),
+    };
+
+    let (path, is_junit) = match option_value(test_cmd, "--output-junit") {
+        Some(file) => (build_dir.join(file), true),
+        None => (build_dir.join(LAST_TEST_LOG), false),
+    };
+
+    let modified = std::fs::metadata(&path).and_then(|m| m.modified());
+    match modified {
+        Ok(modified) if results::written_since(modified, since) => {}
+        Ok(_) => {
+            eprintln!("Warning: ignoring stale ctest results {} (not written by this run)", path.display());
+            return None;
+        }
+        Err(e) => {
+            eprintln!("Warning: no ctest results found at {}: {}", path.display(), e);
+            return None;
+        }
+    }
+
+    let parsed = if is_junit {
+        junit::parse_file(&path)
+    } else {
+        std::fs::read_to_string(&path)
+            .map(|content| parse_last_test_log(&content))
+            .map_err(|e| e.to_string())
+    };
+
+    match parsed {
+        Ok(cases) => Some(cases),
+        Err(e) => {
+            eprintln!("Warning: failed to parse ctest results {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Value of a `--option value` or `--option=value` argument
+fn option_value(test_cmd: &[String], option: &str) -> Option<PathBuf> {
+    let mut args = test_cmd.iter().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == option {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(value) = arg.strip_prefix(option).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(PathBuf::from(value));
+        }
+    }
+    None
+}
+
+/// Parse the per-test sections of a ctest `LastTest.log`
+pub fn parse_last_test_log(content: &str) -> Vec<TestCase> {
+    let mut cases = Vec::new();
+    let mut current: Option<TestCase> = None;
+    let mut in_output = false;
+
+    for line in content.lines() {
+        let line = line.trim_end();
+
+        // The test's own output may contain anything, including lines that
+        // look like ctest status lines
+        if in_output {
+            in_output = line != "<end of output>";
+            continue;
+        }
+        if line == "Output:" && current.is_some() {
+            in_output = true;
+            continue;
+        }
+
+        // "1/3 Test: name" starts a new section ("1/3 Testing: name" precedes it)
+        if let Some(name) = test_header(line) {
+            if current.as_ref().map(|case| case.name.as_str()) != Some(name) {
+                current = Some(TestCase {
+                    name: name.to_string(),
+                    status: TestStatus::Failed,
+                    duration: None,
+                    message: None,
+                });
+            }
+            continue;
+        }
+
+        let Some(case) = current.as_mut() else {
+            continue;
+        };
+
+        if let Some(time) = line.strip_prefix("Test time =") {
+            case.duration = time
+                .trim()
+                .trim_end_matches("sec")
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+        } else if let Some(status) = line.strip_prefix("Test ").filter(|_| line.ends_with('.')) {
+            let status = status.trim_end_matches('.');
+            case.status = match status.to_ascii_lowercase().as_str() {
+                "passed" => TestStatus::Passed,
+                "skipped" | "not run" => TestStatus::Skipped,
+                _ => TestStatus::Failed,
+            };
+            if case.status == TestStatus::Failed && status != "Failed" {
+                case.message = Some(status.to_string());
+            }
+            cases.extend(current.take());
+        }
+    }
+
+    cases
+}
+
+/// Test name from a "N/M Test: name" or "N/M Testing: name" line
+fn test_header(line: &str) -> Option<&str> {
+    let (counter, rest) = line.split_once(' ')?;
+    let (index, total) = counter.split_once('/')?;
+    if !(index.chars().all(|c| c.is_ascii_digit()) && total.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    rest.strip_prefix("Test: ")
+        .or_else(|| rest.strip_prefix("Testing: "))
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const LAST_TEST_LOG_SAMPLE: &str = r#"Start testing: Oct 18 12:00 UTC
+----------------------------------------------------------
+1/2 Testing: list_push
+1/2 Test: list_push
+Command: "/build/list_push"
+Directory: /build
+"list_push" start time: Oct 18 12:00 UTC
+Output:
+----------------------------------------------------------
+Test Failed.
+<end of output>
+Test time =   0.02 sec
+----------------------------------------------------------
+Test Passed.
+"list_push" end time: Oct 18 12:00 UTC
+"list_push" time elapsed: 00:00:00
+----------------------------------------------------------
+
+2/2 Testing: list_pop
+2/2 Test: list_pop
+Command: "/build/list_pop"
+Directory: /build
+"list_pop" start time: Oct 18 12:00 UTC
+Output:
+----------------------------------------------------------
+assertion failed
+<end of output>
+Test time =   1.50 sec
+----------------------------------------------------------
+Test Failed.
+"list_pop" end time: Oct 18 12:00 UTC
+"list_pop" time elapsed: 00:00:01
+----------------------------------------------------------
+
+End testing: Oct 18 12:00 UTC
+"#;
+
+    #[test]
+    fn test_parse_last_test_log() {
+        let cases = parse_last_test_log(LAST_TEST_LOG_SAMPLE);
+        assert_eq!(cases.len(), 2);
+
+        assert_eq!(cases[0].name, "list_push");
+        assert_eq!(cases[0].status, TestStatus::Passed);
+        assert_eq!(cases[0].duration, Some(Duration::from_millis(20)));
+
+        assert_eq!(cases[1].name, "list_pop");
+        assert_eq!(cases[1].status, TestStatus::Failed);
+        assert_eq!(cases[1].duration, Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn test_is_ctest_command() {
+        let cmd = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(is_ctest_command(&cmd(&["ctest", "--output-on-failure"])));
+        assert!(is_ctest_command(&cmd(&["/usr/bin/ctest"])));
+        assert!(!is_ctest_command(&cmd(&["make", "test"])));
+    }
+
+    #[test]
+    fn test_collect_prefers_junit_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let since = SystemTime::now() - Duration::from_secs(60);
+        std::fs::write(
+            temp_dir.path().join("ctest.xml"),
+            r#"<testsuite><testcase name="a" time="0.1"/><testcase name="b"><failure/></testcase></testsuite>"#,
+        )
+        .unwrap();
+
+        let cmd = vec!["ctest".to_string(), "--output-junit".to_string(), "ctest.xml".to_string()];
+        let cases = collect(&cmd, temp_dir.path(), since).unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[1].status, TestStatus::Failed);
+
+        // Without JUnit output, LastTest.log is read
+        let log_dir = temp_dir.path().join("Testing/Temporary");
+        std::fs::create_dir_all(&log_dir).unwrap();
+        std::fs::write(log_dir.join("LastTest.log"), LAST_TEST_LOG_SAMPLE).unwrap();
+        let cases = collect(&["ctest".to_string()], temp_dir.path(), since).unwrap();
+        assert_eq!(cases.len(), 2);
+
+        // Written just before the run started by the file clock's reckoning
+        let log = std::fs::File::options().write(true).open(log_dir.join("LastTest.log")).unwrap();
+        let now = SystemTime::now();
+        log.set_modified(now - Duration::from_millis(500)).unwrap();
+        assert!(collect(&["ctest".to_string()], temp_dir.path(), now).is_some());
+        log.set_modified(now - Duration::from_secs(60)).unwrap();
+        assert!(collect(&["ctest".to_string()], temp_dir.path(), now).is_none());
+    }
+}
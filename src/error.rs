@@ -1,12 +1,18 @@
 use std::fmt;
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     ConfigToolNotFound,
     CommandExecutionFailed(String, Option<i32>),
+    CommandTerminatedBySignal(String, i32),
+    TimedOut(String),
+    TestsFailed(String),
     ConfigSaveFailed(String),
+    ConfigLoadFailed(String),
+    GitFailed(String),
+    LockFailed(String),
     IoError(std::io::Error),
-    ProjectRootNotFound(String),
 }
 
 impl Error {
@@ -15,13 +21,19 @@ impl Error {
         match self {
             Error::ConfigToolNotFound => 1,
             Error::CommandExecutionFailed(_, Some(code)) => *code,
-            // When no exit code is available (signal termination), use 128
-            // This is a common convention on Unix systems (128 + signal number)
-            // but since we don't have the signal number, we use 128
+            // No exit code and no signal number is available (e.g. the command
+            // could not be started), use 128
             Error::CommandExecutionFailed(_, None) => 128,
+            // Shell convention: 128 + signal number
+            Error::CommandTerminatedBySignal(_, signal) => 128 + signal,
+            // Same exit code as coreutils `timeout`
+            Error::TimedOut(_) => 124,
+            Error::TestsFailed(_) => 1,
             Error::ConfigSaveFailed(_) => 1,
+            Error::ConfigLoadFailed(_) => 1,
+            Error::GitFailed(_) => 1,
+            Error::LockFailed(_) => 1,
             Error::IoError(_) => 1,
-            Error::ProjectRootNotFound(_) => 1,
         }
     }
 }
@@ -35,15 +47,30 @@ impl fmt::Display for Error {
             Error::CommandExecutionFailed(msg, _) => {
                 write!(f, "Command execution failed: {}", msg)
             }
+            Error::CommandTerminatedBySignal(msg, _) => {
+                write!(f, "Command execution failed: {}", msg)
+            }
+            Error::TimedOut(msg) => {
+                write!(f, "Command timed out: {}", msg)
+            }
+            Error::TestsFailed(msg) => {
+                write!(f, "Tests failed: {}", msg)
+            }
             Error::ConfigSaveFailed(msg) => {
                 write!(f, "Failed to save configuration: {}", msg)
             }
+            Error::ConfigLoadFailed(msg) => {
+                write!(f, "Failed to read configuration: {}", msg)
+            }
+            Error::GitFailed(msg) => {
+                write!(f, "Git operation failed: {}", msg)
+            }
+            Error::LockFailed(msg) => {
+                write!(f, "Failed to lock .c2rust: {}", msg)
+            }
             Error::IoError(err) => {
                 write!(f, "IO error: {}", err)
             }
-            Error::ProjectRootNotFound(msg) => {
-                write!(f, "{}", msg)
-            }
         }
     }
 }
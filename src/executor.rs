@@ -1,9 +1,121 @@
 use crate::error::{Error, Result};
+use crate::tap::TapParser;
+use std::fs::File;
+use std::io::{Read, Write};
 use std::path::Path;
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Shared handle to the run log, written to by both output streams.
+/// Set to `None` once a write fails, so logging stops for both streams.
+type LogFile = Arc<Mutex<Option<File>>>;
+
+/// How often a running command is polled while a timeout is active
+const TIMEOUT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long the output pipes may stay silent after the command exited before
+/// c2rust-test stops waiting for them: a background process the command left
+/// running can hold them open indefinitely
+const OUTPUT_IDLE_GRACE: Duration = Duration::from_millis(500);
+
+/// Process group of the running test command, read by the signal handler.
+/// Zero while no command is running.
+static CHILD_PGID: AtomicI32 = AtomicI32::new(0);
+
+/// Signals that are forwarded to the test command's process group
+#[cfg(unix)]
+const FORWARDED_SIGNALS: [libc::c_int; 2] = [libc::SIGINT, libc::SIGTERM];
+
+/// Optional behaviour for `execute_command`
+#[derive(Default)]
+pub struct ExecOptions<'a> {
+    /// Copy stdout and stderr into this file while still showing them on the terminal
+    pub log_path: Option<&'a Path>,
+    /// Kill the command's whole process group if it runs longer than this
+    pub timeout: Option<Duration>,
+    /// Feed every line the command writes to stdout into this TAP parser
+    pub tap: Option<Arc<Mutex<TapParser>>>,
+    /// Extra environment variables set for the command
+    pub env: &'a [(String, String)],
+    /// Run the command as a script through this shell (`<shell> -c <script>`)
+    pub shell: Option<&'a str>,
+    /// Keep a copy of stdout and stderr in memory for comparing runs
+    pub capture: Option<CapturedOutput>,
+}
+
+/// In-memory copy of a command's stdout and stderr, filled in by `execute_command`.
+/// Clones share the same buffers.
+#[derive(Debug, Default, Clone)]
+pub struct CapturedOutput {
+    stdout: Arc<Mutex<Vec<u8>>>,
+    stderr: Arc<Mutex<Vec<u8>>>,
+}
+
+impl CapturedOutput {
+    pub fn stdout(&self) -> String {
+        String::from_utf8_lossy(&self.stdout.lock().unwrap_or_else(|e| e.into_inner())).into_owned()
+    }
+
+    pub fn stderr(&self) -> String {
+        String::from_utf8_lossy(&self.stderr.lock().unwrap_or_else(|e| e.into_inner())).into_owned()
+    }
+}
+
+/// How the test command itself ended, before c2rust-test judges the run
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CommandStatus {
+    /// Exit code; `None` if the command was killed or never started
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the command
+    pub signal: Option<i32>,
+    pub timed_out: bool,
+}
+
+impl CommandStatus {
+    /// The status `execute_command` reported through its result
+    pub fn of(result: &Result<()>) -> Self {
+        match result {
+            Ok(()) => CommandStatus { exit_code: Some(0), ..Default::default() },
+            Err(Error::CommandExecutionFailed(_, code)) => CommandStatus { exit_code: *code, ..Default::default() },
+            Err(Error::CommandTerminatedBySignal(_, signal)) => {
+                CommandStatus { signal: Some(*signal), ..Default::default() }
+            }
+            Err(Error::TimedOut(_)) => CommandStatus { timed_out: true, ..Default::default() },
+            Err(_) => CommandStatus::default(),
+        }
+    }
+}
+
+/// Shell used by `--shell` when none was recorded: `$SHELL`, falling back to `/bin/sh`
+pub fn default_shell() -> String {
+    std::env::var("SHELL")
+        .ok()
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| "/bin/sh".to_string())
+}
+
+/// The script of a shell-mode command. It must be a single argument: joining
+/// several would lose their quoting, unlike the argv of a direct command.
+pub fn shell_script(command: &[String]) -> std::result::Result<&str, String> {
+    match command {
+        [script] => Ok(script),
+        _ => Err(format!(
+            "shell mode takes the script as a single argument, e.g. 'make && ./run_tests', got {} arguments",
+            command.len()
+        )),
+    }
+}
+
+/// Build the argv that runs `script` through `shell`
+pub fn shell_command(shell: &str, script: &str) -> Vec<String> {
+    vec![shell.to_string(), "-c".to_string(), script.to_string()]
+}
 
 /// Execute a command in the specified directory with real-time output
-pub fn execute_command(dir: &Path, command: &[String]) -> Result<()> {
+pub fn execute_command(dir: &Path, command: &[String], options: &ExecOptions) -> Result<()> {
     if command.is_empty() {
         return Err(Error::CommandExecutionFailed(
             "No command provided".to_string(),
@@ -11,91 +123,889 @@ pub fn execute_command(dir: &Path, command: &[String]) -> Result<()> {
         ));
     }
 
+    let shell_argv;
+    let command = match options.shell {
+        Some(shell) => {
+            let script = shell_script(command).map_err(|e| Error::CommandExecutionFailed(e, None))?;
+            shell_argv = shell_command(shell, script);
+            &shell_argv[..]
+        }
+        None => command,
+    };
+
     let program = &command[0];
     let args = &command[1..];
 
     // Print the command being executed
-    println!("Executing command: {} {}", program, args.join(" "));
+    // Shell-quoted so that the printed command can be pasted back into a shell
+    let display = shell_words::join(command);
+    println!("Executing command: {}", display);
     println!("In directory: {}", dir.display());
     println!();
 
-    // Spawn the command with inherited stdout/stderr for real-time output
-    let mut child = Command::new(program)
-        .args(args)
+    let log_path = options.log_path;
+    let log = log_path.and_then(|path| open_log(path, command, dir, options.env));
+
+    // Streams nobody needs to observe are inherited for real-time output; the
+    // others are piped so they can be copied to the terminal, the log file, the
+    // capture buffers and (for stdout) the TAP parser. A piped stdout is not a
+    // tty, so C stdio fully buffers it; the README documents `stdbuf -oL`.
+    let pipe_if = |needed: bool| if needed { Stdio::piped() } else { Stdio::inherit() };
+    let capture = options.capture.as_ref();
+    let stdout_cfg = pipe_if(log.is_some() || options.tap.is_some() || capture.is_some());
+    let stderr_cfg = pipe_if(log.is_some() || capture.is_some());
+
+    let mut cmd = Command::new(program);
+    cmd.args(args)
+        .envs(options.env.iter().map(|(key, value)| (key, value)))
         .current_dir(dir)
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
+        .stdout(stdout_cfg)
+        .stderr(stderr_cfg);
+
+    // Run the command in its own process group so that a timeout or an
+    // interrupt reaches everything it started, not just the direct child
+    #[cfg(unix)]
+    let take_terminal = stdin_is_foreground_tty();
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+        if take_terminal {
+            // The new group must own the terminal before the command can read
+            // from it, or it is stopped by SIGTTIN. The parent sets it too (see
+            // `TerminalForeground`); whichever runs first wins the race.
+            // SAFETY: the closure only calls getpid, pthread_sigmask and tcsetpgrp,
+            // which are async-signal-safe
+            unsafe {
+                cmd.pre_exec(|| {
+                    set_foreground(libc::getpid());
+                    Ok(())
+                });
+            }
+        }
+    }
+
+    let mut child = cmd
         .spawn()
         .map_err(|e| {
             Error::CommandExecutionFailed(
                 format!(
-                    "Failed to execute command '{} {}': {}",
-                    program,
-                    args.join(" "),
+                    "Failed to execute command '{}': {}",
+                    display,
                     e
                 ),
                 None,
             )
         })?;
 
+    // The timeout covers the output left to forward after the command exited, too
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+
+    // If the command has the terminal, Ctrl-C reaches it directly; otherwise
+    // only c2rust-test receives it, so pass it on until the command has exited
+    let _forwarder = SignalForwarder::install(&child);
+    #[cfg(unix)]
+    let mut foreground = take_terminal.then(|| TerminalForeground::hand_to(&child));
+    // Ctrl-Z stops the command's process group, which c2rust-test is not part of
+    #[cfg(unix)]
+    let child_pgid = child.id() as libc::pid_t;
+    let mut on_stop = || {
+        #[cfg(unix)]
+        suspend_with(child_pgid, foreground.as_mut());
+    };
+
+    let mut tees = Tees::default();
+    if let Some(stdout) = child.stdout.take() {
+        let log = log.clone();
+        let tap = options.tap.clone();
+        let capture = capture.map(|capture| capture.stdout.clone());
+        tees.spawn(move |activity| tee(stdout, std::io::stdout(), log, tap, capture, activity));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        let log = log.clone();
+        let capture = capture.map(|capture| capture.stderr.clone());
+        tees.spawn(move |activity| tee(stderr, std::io::stderr(), log, None, capture, activity));
+    }
+
     // Wait for the command to complete
-    let status = child.wait().map_err(|e| {
+    let status = wait_with_timeout(&mut child, options.timeout, &mut on_stop).map_err(|e| {
         Error::CommandExecutionFailed(
             format!(
-                "Failed to wait for command '{} {}': {}",
-                program,
-                args.join(" "),
+                "Failed to wait for command '{}': {}",
+                display,
                 e
             ),
             None,
         )
     })?;
 
+    let status = match status {
+        Some(status) => status,
+        None => {
+            let timeout = options.timeout.unwrap_or_default();
+            kill_process_group(&mut child);
+            let _ = tees.join(deadline);
+            if let Some(log) = &log {
+                write_log(log, format!("\n# timed out after {:?}\n", timeout).as_bytes());
+            }
+
+            println!();
+            println!("Timed out after {:?}", timeout);
+            println!();
+
+            return Err(Error::TimedOut(format!(
+                "Command '{}' did not finish within {:?}",
+                display,
+                timeout,
+            )));
+        }
+    };
+
+    // A broken terminal or log write must not turn a finished test run into a failure
+    if let Err(e) = tees.join(deadline) {
+        eprintln!("Warning: failed to forward command output: {}", e);
+    }
+
+    let signal = exit_signal(&status);
+
+    if let Some(log) = &log {
+        let footer = match (status.code(), signal) {
+            (Some(code), _) => format!("\n# exit code: {}\n", code),
+            (None, Some(sig)) => format!("\n# terminated by signal {}\n", signal_name(sig)),
+            (None, None) => "\n# terminated by signal\n".to_string(),
+        };
+        write_log(log, footer.as_bytes());
+    }
+
     // Print exit status
     println!();
-    if let Some(code) = status.code() {
-        println!("Exit code: {}", code);
-    } else {
-        println!("Process terminated by signal");
+    match (status.code(), signal) {
+        (Some(code), _) => println!("Exit code: {}", code),
+        (None, Some(sig)) => println!("Process terminated by signal {}", signal_name(sig)),
+        (None, None) => println!("Process terminated by signal"),
+    }
+    if let Some(path) = log_path.filter(|_| log.is_some()) {
+        println!("Log file: {}", path.display());
     }
     println!();
 
     if !status.success() {
+        if let Some(sig) = signal {
+            return Err(Error::CommandTerminatedBySignal(
+                format!(
+                    "Command '{}' was terminated by signal {}",
+                    display,
+                    signal_name(sig),
+                ),
+                sig,
+            ));
+        }
+
         let error_msg = if let Some(code) = status.code() {
             format!(
-                "Command '{} {}' failed with exit code {}",
-                program,
-                args.join(" "),
+                "Command '{}' failed with exit code {}",
+                display,
                 code,
             )
         } else {
             format!(
-                "Command '{} {}' was terminated by signal",
-                program,
-                args.join(" "),
+                "Command '{}' was terminated by signal",
+                display,
             )
         };
-        
+
         return Err(Error::CommandExecutionFailed(error_msg, status.code()));
     }
 
     Ok(())
 }
 
+/// Wait for the child to exit, giving up once `timeout` has elapsed.
+/// Returns `Ok(None)` if the timeout expired while the child was still running.
+/// `on_stop` is called whenever the child is stopped by job control.
+fn wait_with_timeout(
+    child: &mut Child,
+    timeout: Option<Duration>,
+    on_stop: &mut dyn FnMut(),
+) -> std::io::Result<Option<ExitStatus>> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        match wait_event(child, deadline.is_none())? {
+            WaitEvent::Exited(status) => return Ok(Some(status)),
+            WaitEvent::Stopped => on_stop(),
+            WaitEvent::Running => {
+                let now = Instant::now();
+                let deadline = deadline.expect("only a timed wait polls");
+                if now >= deadline {
+                    return Ok(None);
+                }
+                thread::sleep(TIMEOUT_POLL_INTERVAL.min(deadline - now));
+            }
+        }
+    }
+}
+
+/// What a single wait for the child observed
+enum WaitEvent {
+    Exited(ExitStatus),
+    /// Stopped by a signal such as SIGTSTP; the child is still alive
+    Stopped,
+    /// Still running; only returned by a non-blocking wait
+    Running,
+}
+
+/// Wait for the child to exit or, on Unix, to be stopped. The child is reaped
+/// with waitpid(2) directly, since `Child::wait` does not report stops; its
+/// `Child` must not be waited on afterwards.
+#[cfg(unix)]
+fn wait_event(child: &Child, block: bool) -> std::io::Result<WaitEvent> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let flags = libc::WUNTRACED | if block { 0 } else { libc::WNOHANG };
+    let mut status = 0;
+    loop {
+        // SAFETY: waitpid only writes the status of our own child into `status`
+        match unsafe { libc::waitpid(child.id() as libc::pid_t, &mut status, flags) } {
+            -1 => {
+                let err = std::io::Error::last_os_error();
+                if err.kind() != std::io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            0 => return Ok(WaitEvent::Running),
+            _ if libc::WIFSTOPPED(status) => return Ok(WaitEvent::Stopped),
+            _ => return Ok(WaitEvent::Exited(ExitStatus::from_raw(status))),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn wait_event(child: &mut Child, block: bool) -> std::io::Result<WaitEvent> {
+    if block {
+        return child.wait().map(WaitEvent::Exited);
+    }
+    Ok(child.try_wait()?.map_or(WaitEvent::Running, WaitEvent::Exited))
+}
+
+/// Kill the child and every process in its process group, then reap the child
+fn kill_process_group(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        // The child was started as the leader of its own process group, so its
+        // pid is also the process group id
+        let pgid = child.id() as libc::pid_t;
+        // SAFETY: kill(2) has no memory-safety preconditions; a negative pid
+        // addresses the process group
+        unsafe {
+            libc::kill(-pgid, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Signal that terminated the process, if it was killed by one
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+/// Human-readable signal description, e.g. "SIGSEGV (11)"
+pub fn signal_name(signal: i32) -> String {
+    #[cfg(unix)]
+    let name = match signal {
+        libc::SIGHUP => Some("SIGHUP"),
+        libc::SIGINT => Some("SIGINT"),
+        libc::SIGQUIT => Some("SIGQUIT"),
+        libc::SIGILL => Some("SIGILL"),
+        libc::SIGTRAP => Some("SIGTRAP"),
+        libc::SIGABRT => Some("SIGABRT"),
+        libc::SIGBUS => Some("SIGBUS"),
+        libc::SIGFPE => Some("SIGFPE"),
+        libc::SIGKILL => Some("SIGKILL"),
+        libc::SIGUSR1 => Some("SIGUSR1"),
+        libc::SIGSEGV => Some("SIGSEGV"),
+        libc::SIGUSR2 => Some("SIGUSR2"),
+        libc::SIGPIPE => Some("SIGPIPE"),
+        libc::SIGALRM => Some("SIGALRM"),
+        libc::SIGTERM => Some("SIGTERM"),
+        libc::SIGXCPU => Some("SIGXCPU"),
+        libc::SIGXFSZ => Some("SIGXFSZ"),
+        libc::SIGSYS => Some("SIGSYS"),
+        _ => None,
+    };
+    #[cfg(not(unix))]
+    let name: Option<&str> = None;
+
+    match name {
+        Some(name) => format!("{} ({})", name, signal),
+        None => format!("signal {}", signal),
+    }
+}
+
+/// Forwards SIGINT/SIGTERM to the test command's process group while alive,
+/// restoring the previous handlers when dropped
+struct SignalForwarder {
+    #[cfg(unix)]
+    previous: Vec<(libc::c_int, libc::sigaction)>,
+}
+
+impl SignalForwarder {
+    fn install(child: &Child) -> Self {
+        CHILD_PGID.store(child.id() as i32, Ordering::SeqCst);
+
+        #[cfg(unix)]
+        {
+            let mut previous = Vec::new();
+            for sig in FORWARDED_SIGNALS {
+                // SAFETY: the handler only performs an atomic load and kill(2),
+                // both of which are async-signal-safe
+                unsafe {
+                    let mut action: libc::sigaction = std::mem::zeroed();
+                    action.sa_sigaction = forward_signal as *const () as libc::sighandler_t;
+                    action.sa_flags = libc::SA_RESTART;
+                    libc::sigemptyset(&mut action.sa_mask);
+                    let mut old: libc::sigaction = std::mem::zeroed();
+                    if libc::sigaction(sig, &action, &mut old) == 0 {
+                        previous.push((sig, old));
+                    }
+                }
+            }
+            SignalForwarder { previous }
+        }
+        #[cfg(not(unix))]
+        SignalForwarder {}
+    }
+}
+
+impl Drop for SignalForwarder {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        for (sig, old) in &self.previous {
+            // SAFETY: restores an action previously returned by sigaction(2)
+            unsafe {
+                libc::sigaction(*sig, old, std::ptr::null_mut());
+            }
+        }
+        CHILD_PGID.store(0, Ordering::SeqCst);
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn forward_signal(sig: libc::c_int) {
+    let pgid = CHILD_PGID.load(Ordering::SeqCst);
+    if pgid > 0 {
+        // SAFETY: kill(2) is async-signal-safe
+        unsafe {
+            libc::kill(-pgid, sig);
+        }
+    }
+}
+
+/// Whether stdin is a terminal on which c2rust-test is the foreground job,
+/// i.e. whether the test command could read from it
+#[cfg(unix)]
+fn stdin_is_foreground_tty() -> bool {
+    // SAFETY: isatty, tcgetpgrp and getpgrp only inspect process state
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 && libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp() }
+}
+
+/// Make `pgid` the foreground process group of the terminal on stdin.
+/// SIGTTOU is blocked meanwhile, since a background group calling tcsetpgrp
+/// would otherwise be stopped by it.
+#[cfg(unix)]
+fn set_foreground(pgid: libc::pid_t) {
+    // SAFETY: only async-signal-safe calls on a stack-allocated signal set;
+    // the previous mask is restored before returning
+    unsafe {
+        let mut block: libc::sigset_t = std::mem::zeroed();
+        let mut previous: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut block);
+        libc::sigaddset(&mut block, libc::SIGTTOU);
+        libc::pthread_sigmask(libc::SIG_BLOCK, &block, &mut previous);
+        libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
+        libc::pthread_sigmask(libc::SIG_SETMASK, &previous, std::ptr::null_mut());
+    }
+}
+
+/// Gives the test command's process group the terminal, so that a test reading
+/// from a tty stdin is not stopped, and takes it back for c2rust-test when dropped.
+///
+/// While the command has the terminal, c2rust-test is a background job that
+/// still forwards the command's output to it, so SIGTTOU is ignored: with
+/// `stty tostop` the first write would stop c2rust-test otherwise.
+#[cfg(unix)]
+struct TerminalForeground {
+    /// c2rust-test's own process group
+    previous: libc::pid_t,
+    child: libc::pid_t,
+    /// SIGTTOU action to restore once c2rust-test has the terminal again
+    ttou_action: Option<libc::sigaction>,
+}
+
+#[cfg(unix)]
+impl TerminalForeground {
+    fn hand_to(child: &Child) -> Self {
+        let mut foreground = TerminalForeground {
+            // SAFETY: getpgrp only inspects process state
+            previous: unsafe { libc::getpgrp() },
+            child: child.id() as libc::pid_t,
+            ttou_action: None,
+        };
+        foreground.hand_over();
+        foreground
+    }
+
+    /// Give the terminal to the command, if c2rust-test has it. The command may
+    /// already have taken it itself (see `execute_command`).
+    fn hand_over(&mut self) {
+        // SAFETY: tcgetpgrp only inspects the terminal
+        let owner = unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) };
+        if owner != self.previous && owner != self.child {
+            return;
+        }
+        // SAFETY: installs SIG_IGN and keeps the previous action to restore it
+        unsafe {
+            let mut ignore: libc::sigaction = std::mem::zeroed();
+            ignore.sa_sigaction = libc::SIG_IGN;
+            let mut old: libc::sigaction = std::mem::zeroed();
+            if self.ttou_action.is_none() && libc::sigaction(libc::SIGTTOU, &ignore, &mut old) == 0 {
+                self.ttou_action = Some(old);
+            }
+        }
+        set_foreground(self.child);
+    }
+
+    /// Take the terminal back from the command, if it still has it. A shell
+    /// that regained the terminal while c2rust-test was stopped keeps it.
+    fn take_back(&mut self) {
+        // SAFETY: tcgetpgrp only inspects the terminal
+        if unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) } == self.child {
+            set_foreground(self.previous);
+        }
+        if let Some(old) = self.ttou_action.take() {
+            // SAFETY: restores an action previously returned by sigaction(2)
+            unsafe {
+                libc::sigaction(libc::SIGTTOU, &old, std::ptr::null_mut());
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for TerminalForeground {
+    fn drop(&mut self) {
+        self.take_back();
+    }
+}
+
+/// The test command's process group was stopped, normally by Ctrl-Z while it
+/// had the terminal. Stop c2rust-test as well, so that the shell sees a stopped
+/// job and regains the terminal; once the shell resumes c2rust-test (`fg` or
+/// `bg`), resume the command, handing it the terminal again in the foreground.
+#[cfg(unix)]
+fn suspend_with(pgid: libc::pid_t, foreground: Option<&mut TerminalForeground>) {
+    let mut foreground = foreground;
+    if let Some(foreground) = foreground.as_mut() {
+        foreground.take_back();
+    }
+    // SAFETY: raise and kill have no memory-safety preconditions. With the
+    // default SIGTSTP action, raise returns only once c2rust-test is continued.
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
+    if let Some(foreground) = foreground {
+        foreground.hand_over();
+    }
+    // SAFETY: see above; a negative pid addresses the process group
+    unsafe {
+        libc::kill(-pgid, libc::SIGCONT);
+    }
+}
+
+/// Tracks the `tee` threads copying the command's output
+#[derive(Default)]
+struct Tees {
+    handles: Vec<JoinHandle<std::io::Result<()>>>,
+    activity: Arc<TeeActivity>,
+}
+
+/// State shared between the `tee` threads and the thread waiting for them
+struct TeeActivity {
+    /// When output was last read from any stream, or the command exited
+    last_read: Mutex<Instant>,
+    /// Set once c2rust-test stops waiting; output read afterwards is dropped
+    abandoned: AtomicBool,
+}
+
+impl Default for TeeActivity {
+    fn default() -> Self {
+        TeeActivity {
+            last_read: Mutex::new(Instant::now()),
+            abandoned: AtomicBool::new(false),
+        }
+    }
+}
+
+impl TeeActivity {
+    fn record_read(&self) {
+        *self.last_read.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_read.lock().unwrap_or_else(|e| e.into_inner()).elapsed()
+    }
+}
+
+impl Tees {
+    fn spawn<F>(&mut self, tee: F)
+    where
+        F: FnOnce(Arc<TeeActivity>) -> std::io::Result<()> + Send + 'static,
+    {
+        let activity = self.activity.clone();
+        self.handles.push(thread::spawn(move || tee(activity)));
+    }
+
+    /// Wait for the streams to reach end of file after the command exited.
+    /// Pipes still held open by processes the command left behind are given up
+    /// once they have been silent for `OUTPUT_IDLE_GRACE`, or at the `deadline`
+    /// of a timeout (but never sooner than the grace period from now); their
+    /// threads keep blocking in `read` but no longer forward anything.
+    fn join(self, deadline: Option<Instant>) -> std::io::Result<()> {
+        // The grace period starts no earlier than the command's exit
+        self.activity.record_read();
+        let deadline = deadline.map(|deadline| deadline.max(Instant::now() + OUTPUT_IDLE_GRACE));
+        while !self.handles.iter().all(JoinHandle::is_finished) {
+            let expired = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if expired || self.activity.idle_for() >= OUTPUT_IDLE_GRACE {
+                self.activity.abandoned.store(true, Ordering::SeqCst);
+                eprintln!("Warning: a process left behind by the command still holds its output open; no longer waiting for it");
+                break;
+            }
+            thread::sleep(TIMEOUT_POLL_INTERVAL);
+        }
+
+        let mut result = Ok(());
+        for handle in self.handles.into_iter().filter(JoinHandle::is_finished) {
+            if let Ok(Err(e)) = handle.join() {
+                result = Err(e);
+            }
+        }
+        result
+    }
+}
+
+/// Create the run log and write a header describing the command.
+///
+/// Logging is a convenience: if the file cannot be created a warning is printed
+/// and the command runs without a log.
+fn open_log(path: &Path, command: &[String], dir: &Path, env: &[(String, String)]) -> Option<LogFile> {
+    let create = || -> std::io::Result<File> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(path)?;
+        writeln!(file, "# command: {}", shell_words::join(command))?;
+        writeln!(file, "# directory: {}", dir.display())?;
+        for (key, value) in env {
+            writeln!(file, "# env: {}={}", key, value)?;
+        }
+        writeln!(file, "# started: {}", chrono::Local::now().to_rfc3339())?;
+        writeln!(file)?;
+        Ok(file)
+    };
+
+    match create() {
+        Ok(file) => Some(Arc::new(Mutex::new(Some(file)))),
+        Err(e) => {
+            eprintln!("Warning: failed to create log file {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Append to the run log. Like creating it, writing the log is a convenience:
+/// the first failure (e.g. a full disk) is reported once and logging stops,
+/// while the command's output keeps reaching the terminal.
+fn write_log(log: &LogFile, data: &[u8]) {
+    let mut file = log.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(Err(e)) = file.as_mut().map(|file| file.write_all(data)) {
+        eprintln!("Warning: failed to write the log file, no longer logging this run: {}", e);
+        *file = None;
+    }
+}
+
+/// Copy a child output stream to the terminal and, if present, the log file
+/// and capture buffer. Output is forwarded as soon as it is read; complete
+/// lines are additionally fed to the TAP parser.
+fn tee<R: Read, W: Write>(
+    mut src: R,
+    mut dst: W,
+    log: Option<LogFile>,
+    tap: Option<Arc<Mutex<TapParser>>>,
+    capture: Option<Arc<Mutex<Vec<u8>>>>,
+    activity: Arc<TeeActivity>,
+) -> std::io::Result<()> {
+    let mut buf = [0u8; 8192];
+    let mut pending_line = Vec::new();
+    let feed_line = |line: &[u8]| {
+        if let Some(parser) = &tap {
+            if let Ok(mut parser) = parser.lock() {
+                parser.feed_line(&String::from_utf8_lossy(line));
+            }
+        }
+    };
+
+    loop {
+        let n = match src.read(&mut buf) {
+            Ok(0) => {
+                if !pending_line.is_empty() {
+                    feed_line(&pending_line);
+                }
+                return Ok(());
+            }
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if activity.abandoned.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        activity.record_read();
+        dst.write_all(&buf[..n])?;
+        dst.flush()?;
+        if let Some(log) = &log {
+            write_log(log, &buf[..n]);
+        }
+        if let Some(capture) = &capture {
+            if let Ok(mut captured) = capture.lock() {
+                captured.extend_from_slice(&buf[..n]);
+            }
+        }
+        if tap.is_some() {
+            pending_line.extend_from_slice(&buf[..n]);
+            while let Some(pos) = pending_line.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = pending_line.drain(..=pos).collect();
+                feed_line(&line);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
 
     #[test]
     fn test_execute_command_empty() {
-        let result = execute_command(Path::new("."), &[]);
+        let result = execute_command(Path::new("."), &[], &ExecOptions::default());
         assert!(result.is_err());
     }
 
     #[test]
     fn test_execute_command_basic() {
         // Test with a simple command that should succeed
-        let result = execute_command(Path::new("."), &["echo".to_string(), "test".to_string()]);
+        let result = execute_command(
+            Path::new("."),
+            &["echo".to_string(), "test".to_string()],
+            &ExecOptions::default(),
+        );
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_execute_command_writes_log() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("logs").join("run.log");
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo to-stdout; echo to-stderr >&2; exit 3".to_string(),
+        ];
+
+        let options = ExecOptions {
+            log_path: Some(&log_path),
+            ..Default::default()
+        };
+        let result = execute_command(temp_dir.path(), &command, &options);
+        assert_eq!(result.unwrap_err().exit_code(), 3);
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        assert!(log.contains("to-stdout"));
+        assert!(log.contains("to-stderr"));
+        assert!(log.contains("# exit code: 3"));
+    }
+
+    #[test]
+    fn test_execute_command_timeout_kills_process_group() {
+        let temp_dir = TempDir::new().unwrap();
+        let marker = temp_dir.path().join("survived");
+        // The background sleep belongs to the same process group and must be
+        // killed together with the shell
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            format!("(sleep 2; touch {}) & sleep 30", marker.display()),
+        ];
+        let options = ExecOptions {
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        let result = execute_command(temp_dir.path(), &command, &options);
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        let err = result.unwrap_err();
+        assert!(matches!(err, Error::TimedOut(_)));
+        assert_eq!(err.exit_code(), 124);
+
+        thread::sleep(Duration::from_secs(3));
+        assert!(!marker.exists(), "background process outlived the timeout");
+    }
+
+    #[test]
+    fn test_execute_command_timeout_covers_output_of_escaped_processes() {
+        // The chatty loop leaves the process group, so it survives the kill and
+        // keeps the output pipe busy
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "setsid sh -c 'i=0; while [ $i -lt 50 ]; do echo tick; sleep 0.1; i=$((i+1)); done' & sleep 30".to_string(),
+        ];
+        let options = ExecOptions {
+            timeout: Some(Duration::from_millis(500)),
+            capture: Some(CapturedOutput::default()),
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        let result = execute_command(Path::new("."), &command, &options);
+        assert!(matches!(result, Err(Error::TimedOut(_))));
+        assert!(start.elapsed() < Duration::from_secs(3), "took {:?}", start.elapsed());
+    }
+
+    #[test]
+    fn test_execute_command_reports_signal() {
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "kill -SEGV $$".to_string(),
+        ];
+
+        let err = execute_command(Path::new("."), &command, &ExecOptions::default()).unwrap_err();
+        assert!(matches!(err, Error::CommandTerminatedBySignal(_, 11)));
+        assert_eq!(err.exit_code(), 139);
+        assert!(err.to_string().contains("SIGSEGV (11)"));
+    }
+
+    #[test]
+    fn test_signal_name() {
+        assert_eq!(signal_name(6), "SIGABRT (6)");
+        assert_eq!(signal_name(9), "SIGKILL (9)");
+        assert_eq!(signal_name(11), "SIGSEGV (11)");
+        assert_eq!(signal_name(200), "signal 200");
+    }
+
+    #[test]
+    fn test_execute_command_feeds_tap_parser() {
+        let parser = Arc::new(Mutex::new(TapParser::default()));
+        let command = vec![
+            "printf".to_string(),
+            "1..2\nok 1 - a\nnot ok 2 - b".to_string(),
+        ];
+        let options = ExecOptions {
+            tap: Some(parser.clone()),
+            ..Default::default()
+        };
+
+        execute_command(Path::new("."), &command, &options).unwrap();
+
+        let summary = parser.lock().unwrap().summary();
+        assert_eq!(summary.ok, 1);
+        assert_eq!(summary.not_ok, 1);
+    }
+
+    #[test]
+    fn test_execute_command_sets_env() {
+        let env = vec![("C2RUST_TEST_EXAMPLE".to_string(), "42".to_string())];
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "test \"$C2RUST_TEST_EXAMPLE\" = 42".to_string(),
+        ];
+        let options = ExecOptions {
+            env: &env,
+            ..Default::default()
+        };
+
+        assert!(execute_command(Path::new("."), &command, &options).is_ok());
+    }
+
+    #[test]
+    fn test_execute_command_through_shell() {
+        let temp_dir = TempDir::new().unwrap();
+        let command = vec!["echo one > out && echo two | tr a-z A-Z >> out".to_string()];
+        let options = ExecOptions {
+            shell: Some("/bin/sh"),
+            ..Default::default()
+        };
+
+        execute_command(temp_dir.path(), &command, &options).unwrap();
+        let out = std::fs::read_to_string(temp_dir.path().join("out")).unwrap();
+        assert_eq!(out, "one\nTWO\n");
+    }
+
+    #[test]
+    fn test_execute_command_captures_output() {
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo out; echo err >&2; exit 3".to_string(),
+        ];
+        let capture = CapturedOutput::default();
+        let options = ExecOptions {
+            capture: Some(capture.clone()),
+            ..Default::default()
+        };
+
+        let result = execute_command(Path::new("."), &command, &options);
+        assert!(matches!(result, Err(Error::CommandExecutionFailed(_, Some(3)))));
+        assert_eq!(CommandStatus::of(&result).exit_code, Some(3));
+        assert_eq!(capture.stdout(), "out\n");
+        assert_eq!(capture.stderr(), "err\n");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_tee_keeps_forwarding_when_the_log_fails() {
+        // Every write to /dev/full fails with ENOSPC
+        let full = File::options().write(true).open("/dev/full").unwrap();
+        let log: LogFile = Arc::new(Mutex::new(Some(full)));
+        let input = vec![b'x'; 100_000];
+        let mut terminal = Vec::new();
+
+        tee(&input[..], &mut terminal, Some(log.clone()), None, None, Arc::default()).unwrap();
+        assert_eq!(terminal, input);
+        assert!(log.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_shell_command() {
+        let command = vec!["make && ./run".to_string()];
+        let script = shell_script(&command).unwrap();
+        assert_eq!(shell_command("/bin/sh", script), vec!["/bin/sh", "-c", "make && ./run"]);
+
+        let command = vec!["echo".to_string(), "a  b".to_string()];
+        assert!(shell_script(&command).is_err());
+    }
 }
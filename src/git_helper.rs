@@ -1,10 +1,158 @@
 use crate::error::Result;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Prefix of the tags marking known-good baselines: `test-pass/<feature>/<timestamp>`
+const BASELINE_TAG_PREFIX: &str = "test-pass/";
+
+/// Trailers recording each run in its auto-commit message
+const TRAILER_SUBCOMMAND: &str = "C2rust-Subcommand";
+const TRAILER_FEATURE: &str = "C2rust-Feature";
+const TRAILER_STATUS: &str = "C2rust-Status";
+const TRAILER_EXIT_CODE: &str = "C2rust-Exit-Code";
+const TRAILER_TEST_DIR: &str = "C2rust-Test-Dir";
+const TRAILER_COMMAND: &str = "C2rust-Command";
+const TRAILER_DURATION: &str = "C2rust-Duration";
+
+/// Longest command shown in a generated subject line
+const SUBJECT_COMMAND_MAX: usize = 60;
+
+/// Entries of a feature's directory (`<feature>/<entry>`) that c2rust-test writes
+/// while the test command runs, outside the `.c2rust` lock: run logs, golden
+/// output and per-test results. Concurrent invocations may be writing them at
+/// any time, so each run only commits the ones it wrote itself.
+const RUN_OUTPUT_ENTRIES: [&str; 3] = ["test-logs", "golden", "results.json"];
+
+/// When the auto-commit runs, as set by `test.commit_policy` in the c2rust config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitPolicy {
+    /// Commit after every run, including failing ones
+    Always,
+    /// Commit only after a passing run, or a failing one recorded with `--record-on-failure`
+    OnSuccess,
+    /// Never commit; changes are left for a later run or a manual commit
+    Never,
+}
+
+impl std::str::FromStr for CommitPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.trim() {
+            "always" => Ok(CommitPolicy::Always),
+            "on-success" => Ok(CommitPolicy::OnSuccess),
+            "never" => Ok(CommitPolicy::Never),
+            other => Err(format!("unknown commit policy '{}' (expected always, on-success or never)", other)),
+        }
+    }
+}
+
+/// Author of commits and tags when git has no user.name/user.email configured,
+/// as in fresh containers and CI sandboxes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackIdentity {
+    pub name: String,
+    pub email: String,
+}
+
+impl Default for FallbackIdentity {
+    fn default() -> Self {
+        FallbackIdentity {
+            name: "c2rust-test".to_string(),
+            email: "c2rust-test@localhost".to_string(),
+        }
+    }
+}
+
+/// The git identity of the repository or, only if none is configured, the one
+/// `fallback` looks up
+fn signature(
+    repo: &git2::Repository,
+    fallback: &dyn Fn() -> FallbackIdentity,
+) -> std::result::Result<git2::Signature<'static>, String> {
+    repo.signature()
+        .or_else(|_| {
+            let fallback = fallback();
+            git2::Signature::now(&fallback.name, &fallback.email)
+        })
+        .map_err(|e| format!("Failed to get git signature: {}", e))
+}
+
+/// The run that produced the changes being committed
+pub struct CommitInfo<'a> {
+    /// Subcommand that ran the tests ("test" or "run")
+    pub subcommand: &'a str,
+    pub feature: &'a str,
+    pub command: &'a [String],
+    /// Test directory relative to the project root
+    pub test_dir: &'a str,
+    pub passed: bool,
+    pub exit_code: i32,
+    pub duration: Duration,
+    /// Replaces the generated subject line (`--message`)
+    pub subject: Option<&'a str>,
+    /// Looks up the commit author if git has no identity configured
+    pub fallback_identity: &'a dyn Fn() -> FallbackIdentity,
+    /// Files and directories in `.c2rust` that this run wrote outside the lock
+    pub written: &'a [PathBuf],
+}
+
+impl CommitInfo<'_> {
+    /// Commit message: a one-line summary of the run followed by
+    /// `C2rust-*` trailers that tooling can query with `git log`
+    pub fn message(&self) -> String {
+        let command = single_line(&shell_words::join(self.command));
+        let status = if self.passed { "pass" } else { "fail" };
+        let duration = format!("{:.2}s", self.duration.as_secs_f64());
+
+        let subject = match self.subject {
+            Some(subject) => single_line(subject),
+            None => {
+                let shown = if command.chars().count() > SUBJECT_COMMAND_MAX {
+                    let truncated: String = command.chars().take(SUBJECT_COMMAND_MAX - 3).collect();
+                    format!("{}...", truncated)
+                } else {
+                    command.clone()
+                };
+                format!(
+                    "c2rust-test {} [{}]: {} {} ({})",
+                    self.subcommand,
+                    self.feature,
+                    status.to_uppercase(),
+                    shown,
+                    duration
+                )
+            }
+        };
+
+        let trailers = [
+            (TRAILER_SUBCOMMAND, self.subcommand.to_string()),
+            (TRAILER_FEATURE, single_line(self.feature)),
+            (TRAILER_STATUS, status.to_string()),
+            (TRAILER_EXIT_CODE, self.exit_code.to_string()),
+            (TRAILER_TEST_DIR, single_line(self.test_dir)),
+            (TRAILER_COMMAND, command),
+            (TRAILER_DURATION, duration),
+        ];
+        let mut message = format!("{}\n\n", subject);
+        for (key, value) in trailers {
+            message.push_str(&format!("{}: {}\n", key, value));
+        }
+        message
+    }
+}
+
+/// Collapse line breaks so a value fits on a subject or trailer line
+fn single_line(value: &str) -> String {
+    value.split(['\r', '\n']).filter(|part| !part.is_empty()).collect::<Vec<_>>().join(" ")
+}
 
 /// Check if there are any modifications in the .c2rust directory and auto-commit if needed.
 /// 
 /// This function checks the git repository located at <project_root>/.c2rust/.git
-/// for any changes in the .c2rust directory and commits them if changes exist.
+/// for changes made since `before` was taken and commits only those paths.
+/// Changes that were already present are left uncommitted, with a warning.
 /// 
 /// This is a best-effort operation - any errors are logged but do not fail the overall
 /// workflow, since auto-commit is a final-stage convenience feature.
@@ -12,114 +160,466 @@ use std::path::Path;
 /// # Arguments
 /// 
 /// * `project_root` - The absolute path to the project root directory
+/// * `info` - The run that produced the changes, used for the commit message
+/// * `before` - Snapshot of the uncommitted changes taken before the run
 /// 
 /// # Returns
 /// 
-/// Always returns `Ok(())`. Errors are logged to stderr but not propagated.
-pub fn auto_commit_if_modified(project_root: &Path) -> Result<()> {
+/// The id of the new commit, or `None` if nothing was committed. Errors are
+/// logged to stderr but not propagated.
+pub fn auto_commit_if_modified(project_root: &Path, info: &CommitInfo, before: &StatusSnapshot) -> Result<Option<String>> {
     let c2rust_dir = project_root.join(".c2rust");
     let git_dir = c2rust_dir.join(".git");
     
     // Check if .git directory exists
     if !git_dir.exists() || !git_dir.is_dir() {
         // .git doesn't exist, nothing to commit
-        return Ok(());
+        return Ok(None);
     }
     
     // All git operations are best-effort - log errors but don't fail
-    if let Err(e) = try_auto_commit(&c2rust_dir) {
-        eprintln!("Warning: Auto-commit failed: {}", e);
-        eprintln!("Continuing without auto-commit.");
+    match try_auto_commit(&c2rust_dir, &info.message(), info.fallback_identity, before, info.written) {
+        Ok(commit) => Ok(commit.map(|oid| oid.to_string())),
+        Err(e) => {
+            eprintln!("Warning: Auto-commit failed: {}", e);
+            eprintln!("Continuing without auto-commit.");
+            Ok(None)
+        }
     }
-    
-    Ok(())
+}
+
+/// Commit the .c2rust changes made since `before` for an edit that is not a test run,
+/// such as a change to a feature's xfail list. The message is a
+/// `c2rust-test <subcommand> [<feature>]: <summary>` subject with the
+/// subcommand and feature trailers; without a status trailer, `history` skips it.
+///
+/// Returns `Ok(None)` if there is no .c2rust repository or nothing changed.
+pub fn commit_edit(
+    project_root: &Path,
+    subcommand: &str,
+    feature: &str,
+    summary: &str,
+    fallback_identity: &dyn Fn() -> FallbackIdentity,
+    before: &StatusSnapshot,
+    written: &[PathBuf],
+) -> std::result::Result<Option<String>, String> {
+    let c2rust_dir = project_root.join(".c2rust");
+    if !c2rust_dir.join(".git").is_dir() {
+        return Ok(None);
+    }
+    let message = format!(
+        "c2rust-test {} [{}]: {}\n\n{}: {}\n{}: {}\n",
+        subcommand,
+        single_line(feature),
+        single_line(summary),
+        TRAILER_SUBCOMMAND,
+        subcommand,
+        TRAILER_FEATURE,
+        single_line(feature),
+    );
+    let commit = try_auto_commit(&c2rust_dir, &message, fallback_identity, before, written)?;
+    Ok(commit.map(|oid| oid.to_string()))
+}
+
+/// List the uncommitted changes in the .c2rust repository.
+///
+/// Returns `Ok(None)` if `<project_root>/.c2rust/.git` does not exist.
+pub fn pending_changes(project_root: &Path) -> std::result::Result<Option<Vec<String>>, String> {
+    let c2rust_dir = project_root.join(".c2rust");
+    if !c2rust_dir.join(".git").is_dir() {
+        return Ok(None);
+    }
+
+    let repo = git2::Repository::open(&c2rust_dir)
+        .map_err(|e| format!("Failed to open git repository at {}: {}", c2rust_dir.display(), e))?;
+    changed_paths(&repo).map(Some)
+}
+
+/// A tag marking a commit whose run passed
+#[derive(Debug)]
+pub struct Baseline {
+    /// Full tag name, e.g. `test-pass/default/20261018-120000`
+    pub name: String,
+    pub commit: String,
+    pub time: chrono::DateTime<chrono::Local>,
+    /// First line of the tagged commit's message
+    pub summary: String,
+}
+
+/// Tag `commit` in the .c2rust repository as a known-good baseline of `feature`.
+///
+/// The tag is named `test-pass/<feature>/<timestamp>`; an annotated tag also
+/// records who tagged it and when. Returns the tag name.
+pub fn tag_baseline(
+    project_root: &Path,
+    commit: &str,
+    feature: &str,
+    timestamp: &str,
+    annotated: bool,
+    fallback_identity: &dyn Fn() -> FallbackIdentity,
+) -> std::result::Result<String, String> {
+    let c2rust_dir = project_root.join(".c2rust");
+    let repo = git2::Repository::open(&c2rust_dir)
+        .map_err(|e| format!("Failed to open git repository at {}: {}", c2rust_dir.display(), e))?;
+    let target = git2::Oid::from_str(commit)
+        .and_then(|oid| repo.find_object(oid, Some(git2::ObjectType::Commit)))
+        .map_err(|e| format!("Failed to find commit {}: {}", commit, e))?;
+
+    let name = format!("{}{}/{}", BASELINE_TAG_PREFIX, feature, timestamp);
+    if annotated {
+        let sig = signature(&repo, fallback_identity)?;
+        let message = format!("Known-good baseline for feature {}\n", feature);
+        repo.tag(&name, &target, &sig, &message, false)
+    } else {
+        repo.tag_lightweight(&name, &target, false)
+    }
+    .map_err(|e| format!("Failed to create tag {}: {}", name, e))?;
+
+    Ok(name)
+}
+
+/// List the baseline tags in the .c2rust repository, oldest first,
+/// optionally only those of one feature
+pub fn list_baselines(project_root: &Path, feature: Option<&str>) -> std::result::Result<Vec<Baseline>, String> {
+    let c2rust_dir = project_root.join(".c2rust");
+    let repo = git2::Repository::open(&c2rust_dir)
+        .map_err(|e| format!("Failed to open git repository at {}: {}", c2rust_dir.display(), e))?;
+
+    let pattern = match feature {
+        Some(feature) => format!("{}{}/*", BASELINE_TAG_PREFIX, feature),
+        None => format!("{}*", BASELINE_TAG_PREFIX),
+    };
+    let names = repo.tag_names(Some(&pattern))
+        .map_err(|e| format!("Failed to list tags: {}", e))?;
+
+    let mut baselines = Vec::new();
+    for name in names.iter().flatten() {
+        // A '*' in the pattern also matches '/', so filter on the exact feature
+        let Some((tag_feature, _)) = name[BASELINE_TAG_PREFIX.len()..].rsplit_once('/') else {
+            continue;
+        };
+        if feature.is_some_and(|feature| feature != tag_feature) {
+            continue;
+        }
+        let commit = repo
+            .revparse_single(&format!("refs/tags/{}", name))
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| format!("Failed to resolve tag {}: {}", name, e))?;
+        let time = chrono::DateTime::from_timestamp(commit.time().seconds(), 0)
+            .unwrap_or_default()
+            .with_timezone(&chrono::Local);
+        baselines.push(Baseline {
+            name: name.to_string(),
+            commit: commit.id().to_string(),
+            time,
+            summary: commit.summary().unwrap_or_default().to_string(),
+        });
+    }
+    baselines.sort_by(|a, b| a.time.cmp(&b.time).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(baselines)
+}
+
+/// A past test run, read back from the trailers of its auto-commit
+#[derive(Debug)]
+pub struct HistoryEntry {
+    pub commit: String,
+    pub time: chrono::DateTime<chrono::Local>,
+    pub subcommand: String,
+    pub feature: String,
+    pub command: String,
+    /// "pass" or "fail"
+    pub status: String,
+    pub exit_code: Option<i32>,
+    pub duration: Option<String>,
+}
+
+/// List the test runs recorded in the .c2rust repository, newest first.
+///
+/// Commits without `C2rust-*` trailers (manual commits, or auto-commits made
+/// before the trailers were added) are skipped.
+pub fn history(project_root: &Path, feature: Option<&str>, limit: Option<usize>) -> std::result::Result<Vec<HistoryEntry>, String> {
+    let c2rust_dir = project_root.join(".c2rust");
+    let repo = git2::Repository::open(&c2rust_dir)
+        .map_err(|e| format!("Failed to open git repository at {}: {}", c2rust_dir.display(), e))?;
+    if repo.head().is_err() {
+        // Nothing has been committed yet
+        return Ok(Vec::new());
+    }
+
+    let mut revwalk = repo.revwalk()
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+        .and_then(|()| revwalk.push_head())
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+
+    let mut entries = Vec::new();
+    for oid in revwalk {
+        if limit.is_some_and(|limit| entries.len() >= limit) {
+            break;
+        }
+        let commit = oid
+            .and_then(|oid| repo.find_commit(oid))
+            .map_err(|e| format!("Failed to read commit: {}", e))?;
+        let Some(entry) = history_entry(&commit) else {
+            continue;
+        };
+        if feature.is_none_or(|feature| feature == entry.feature) {
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// The run recorded in a commit's trailers, if it has them
+fn history_entry(commit: &git2::Commit) -> Option<HistoryEntry> {
+    let trailers = git2::message_trailers_strs(commit.message()?).ok()?;
+    let trailers: HashMap<&str, &str> = trailers.iter().collect();
+    let trailer = |key: &str| trailers.get(key).map(|value| value.to_string());
+
+    Some(HistoryEntry {
+        commit: commit.id().to_string(),
+        time: chrono::DateTime::from_timestamp(commit.time().seconds(), 0)
+            .unwrap_or_default()
+            .with_timezone(&chrono::Local),
+        subcommand: trailer(TRAILER_SUBCOMMAND).unwrap_or_default(),
+        feature: trailer(TRAILER_FEATURE)?,
+        command: trailer(TRAILER_COMMAND).unwrap_or_default(),
+        status: trailer(TRAILER_STATUS)?,
+        exit_code: trailer(TRAILER_EXIT_CODE).and_then(|code| code.parse().ok()),
+        duration: trailer(TRAILER_DURATION),
+    })
+}
+
+/// Uncommitted changes in the .c2rust repository at one point in time.
+///
+/// Taken before the test command runs, so the auto-commit can tell the paths
+/// this run touched apart from changes that were already there.
+#[derive(Debug, Default)]
+pub struct StatusSnapshot {
+    /// Content hash of each changed path, or `None` if it was deleted
+    files: HashMap<String, Option<git2::Oid>>,
+}
+
+impl StatusSnapshot {
+    /// Record the current uncommitted changes in `<project_root>/.c2rust`.
+    /// An empty snapshot is returned if there is no .c2rust repository.
+    pub fn take(project_root: &Path) -> std::result::Result<Self, String> {
+        let c2rust_dir = project_root.join(".c2rust");
+        if !c2rust_dir.join(".git").is_dir() {
+            return Ok(Self::default());
+        }
+        let repo = git2::Repository::open(&c2rust_dir)
+            .map_err(|e| format!("Failed to open git repository at {}: {}", c2rust_dir.display(), e))?;
+        Self::of_repo(&repo)
+    }
+
+    fn of_repo(repo: &git2::Repository) -> std::result::Result<Self, String> {
+        let workdir = repo.workdir().ok_or("Repository has no working directory")?;
+        let files = changed_paths(repo)?
+            .into_iter()
+            .map(|path| {
+                let hash = hash_worktree_file(&workdir.join(&path));
+                (path, hash)
+            })
+            .collect();
+        Ok(Self { files })
+    }
+}
+
+/// Paths with uncommitted changes (staged, unstaged or untracked), ignoring ignored
+/// files and the c2rust-test lock file
+fn changed_paths(repo: &git2::Repository) -> std::result::Result<Vec<String>, String> {
+    let mut options = git2::StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    let statuses = repo.statuses(Some(&mut options))
+        .map_err(|e| format!("Failed to read git status: {}", e))?;
+
+    Ok(statuses
+        .iter()
+        .filter(|entry| !entry.status().is_ignored())
+        .filter_map(|entry| entry.path().map(str::to_string))
+        .filter(|path| path != crate::lock::LOCK_FILE)
+        .collect())
+}
+
+/// Whether a path relative to `.c2rust` is run output written outside the lock
+fn is_run_output(path: &str) -> bool {
+    let mut components = path.split('/');
+    components.next();
+    components.next().is_some_and(|entry| RUN_OUTPUT_ENTRIES.contains(&entry))
+}
+
+/// Blob id of a working tree file, or `None` if it does not exist
+fn hash_worktree_file(path: &Path) -> Option<git2::Oid> {
+    git2::Oid::hash_file(git2::ObjectType::Blob, path).ok()
 }
 
 /// Internal helper that performs the actual git operations.
 /// Errors are returned to the caller for logging.
-fn try_auto_commit(c2rust_dir: &Path) -> std::result::Result<(), String> {
+///
+/// Only the paths whose content changed since `before` was taken are committed,
+/// and of the run output (see `RUN_OUTPUT_ENTRIES`) only what is in `written`
+/// or was left uncommitted by an earlier run and has not changed since.
+/// The commit is built from HEAD's tree in an in-memory index, so changes that
+/// were already staged or modified before the run stay out of it.
+fn try_auto_commit(
+    c2rust_dir: &Path,
+    message: &str,
+    fallback_identity: &dyn Fn() -> FallbackIdentity,
+    before: &StatusSnapshot,
+    written: &[PathBuf],
+) -> std::result::Result<Option<git2::Oid>, String> {
     // Open the repository
     let repo = git2::Repository::open(c2rust_dir)
         .map_err(|e| format!("Failed to open git repository at {}: {}", c2rust_dir.display(), e))?;
-    
-    // Check if there are any modifications
-    let mut index = repo.index()
-        .map_err(|e| format!("Failed to get git index: {}", e))?;
-    
-    // Add all changes to the index
-    index.add_all(["."].iter(), git2::IndexAddOption::DEFAULT, None)
-        .map_err(|e| format!("Failed to add files to git index: {}", e))?;
-    
-    index.write()
-        .map_err(|e| format!("Failed to write git index: {}", e))?;
-    
-    // Get the tree for the index
-    let tree_id = index.write_tree()
-        .map_err(|e| format!("Failed to write tree: {}", e))?;
-    
-    let tree = repo.find_tree(tree_id)
-        .map_err(|e| format!("Failed to find tree: {}", e))?;
-    
-    // Get HEAD commit
-    let head = match repo.head() {
-        Ok(head) => head,
-        Err(_) => {
-            // No HEAD, this might be the first commit
-            // Check if there are any changes to commit
-            let diff = repo.diff_tree_to_index(None, Some(&index), None)
-                .map_err(|e| format!("Failed to create diff: {}", e))?;
-            
-            // If there are no changes, return early
-            if diff.deltas().len() == 0 {
-                return Ok(());
+    let workdir = repo.workdir().ok_or("Repository has no working directory")?.to_path_buf();
+
+    // Split the current changes into the ones this run made and the ones it found
+    let mut touched = Vec::new();
+    let mut preexisting = Vec::new();
+    let written: Vec<&Path> = written.iter().filter_map(|path| path.strip_prefix(c2rust_dir).ok()).collect();
+    for path in changed_paths(&repo)? {
+        let hash = hash_worktree_file(&workdir.join(&path));
+        let others_output =
+            is_run_output(&path) && !written.iter().any(|written| Path::new(&path).starts_with(written));
+        match before.files.get(&path) {
+            // Left for the concurrent invocation that is writing it
+            None if others_output => {}
+            None => touched.push(path),
+            Some(old_hash) if *old_hash != hash && others_output => {}
+            Some(old_hash) if *old_hash != hash => {
+                eprintln!(
+                    "Warning: {} already had uncommitted changes before this run; committing them with this run's changes",
+                    path
+                );
+                touched.push(path);
             }
-            
-            // Create an initial commit
-            let sig = repo.signature()
-                .map_err(|e| format!("Failed to get git signature: {}", e))?;
-            
-            repo.commit(
-                Some("HEAD"),
-                &sig,
-                &sig,
-                "Auto-commit: c2rust-test changes",
-                &tree,
-                &[],
-            ).map_err(|e| format!("Failed to create initial commit: {}", e))?;
-            
-            return Ok(());
+            // Output of an earlier run that was not committed, e.g. a failing
+            // one under the on-success policy: c2rust-test's own, so it is committed
+            Some(_) if is_run_output(&path) => touched.push(path),
+            Some(_) => preexisting.push(path),
         }
+    }
+    if !preexisting.is_empty() {
+        eprintln!(
+            "Warning: leaving {} pre-existing change(s) in .c2rust uncommitted: {}",
+            preexisting.len(),
+            preexisting.join(", ")
+        );
+    }
+    if touched.is_empty() {
+        return Ok(None);
+    }
+
+    // Get HEAD commit, if any; without one this will be the first commit
+    let parent_commit = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()
+            .map_err(|e| format!("Failed to get parent commit: {}", e))?),
+        Err(_) => None,
     };
-    
-    let parent_commit = head.peel_to_commit()
-        .map_err(|e| format!("Failed to get parent commit: {}", e))?;
-    
-    // Check if there are any changes compared to the parent commit
-    let parent_tree = parent_commit.tree()
-        .map_err(|e| format!("Failed to get parent tree: {}", e))?;
-    
-    let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&tree), None)
-        .map_err(|e| format!("Failed to create diff: {}", e))?;
-    
-    // If there are no changes, return early
-    if diff.deltas().len() == 0 {
-        return Ok(());
+    let parent_tree = match &parent_commit {
+        Some(commit) => Some(commit.tree()
+            .map_err(|e| format!("Failed to get parent tree: {}", e))?),
+        None => None,
+    };
+
+    // Stage the touched paths on top of HEAD's tree
+    let mut index = git2::Index::new()
+        .map_err(|e| format!("Failed to create git index: {}", e))?;
+    if let Some(tree) = &parent_tree {
+        index.read_tree(tree)
+            .map_err(|e| format!("Failed to read parent tree: {}", e))?;
     }
-    
+    for path in &touched {
+        stage_path(&repo, &mut index, &workdir, path)?;
+    }
+    let tree_id = index.write_tree_to(&repo)
+        .map_err(|e| format!("Failed to write tree: {}", e))?;
+
+    // The touched paths may have been changed back to their committed content
+    if parent_tree.as_ref().map(|tree| tree.id()) == Some(tree_id) {
+        return Ok(None);
+    }
+    let tree = repo.find_tree(tree_id)
+        .map_err(|e| format!("Failed to find tree: {}", e))?;
+
     // Create the commit
-    let sig = repo.signature()
-        .map_err(|e| format!("Failed to get git signature: {}", e))?;
-    
-    repo.commit(
-        Some("HEAD"),
-        &sig,
-        &sig,
-        "Auto-commit: c2rust-test changes",
-        &tree,
-        &[&parent_commit],
-    ).map_err(|e| format!("Failed to create commit: {}", e))?;
-    
-    Ok(())
+    let sig = signature(&repo, fallback_identity)?;
+    let parents: Vec<&git2::Commit> = parent_commit.iter().collect();
+    let commit = repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+        .map_err(|e| format!("Failed to create commit: {}", e))?;
+
+    // Keep the on-disk index in step for the committed paths only
+    let mut disk_index = repo.index()
+        .map_err(|e| format!("Failed to get git index: {}", e))?;
+    for path in &touched {
+        let result = if workdir.join(path).exists() {
+            disk_index.add_path(Path::new(path))
+        } else {
+            disk_index.remove_path(Path::new(path))
+        };
+        result.map_err(|e| format!("Failed to update git index for {}: {}", path, e))?;
+    }
+    disk_index.write()
+        .map_err(|e| format!("Failed to write git index: {}", e))?;
+
+    Ok(Some(commit))
+}
+
+/// Put the working tree version of `path` into `index`, or remove it if it was deleted.
+/// The index is not backed by the repository, so the blob is written to `repo` directly.
+fn stage_path(repo: &git2::Repository, index: &mut git2::Index, workdir: &Path, path: &str) -> std::result::Result<(), String> {
+    let full_path = workdir.join(path);
+    let metadata = match std::fs::symlink_metadata(&full_path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return index.remove_path(Path::new(path))
+                .map_err(|e| format!("Failed to remove {} from git index: {}", path, e));
+        }
+        Err(e) => return Err(format!("Failed to read {}: {}", full_path.display(), e)),
+    };
+
+    let (mode, content) = if metadata.file_type().is_symlink() {
+        let target = std::fs::read_link(&full_path)
+            .map_err(|e| format!("Failed to read {}: {}", full_path.display(), e))?;
+        (0o120000, target.to_string_lossy().into_owned().into_bytes())
+    } else {
+        let content = std::fs::read(&full_path)
+            .map_err(|e| format!("Failed to read {}: {}", full_path.display(), e))?;
+        (if is_executable(&metadata) { 0o100755 } else { 0o100644 }, content)
+    };
+
+    let id = repo.blob(&content)
+        .map_err(|e| format!("Failed to write blob for {}: {}", path, e))?;
+    let entry = git2::IndexEntry {
+        ctime: git2::IndexTime::new(0, 0),
+        mtime: git2::IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode,
+        uid: 0,
+        gid: 0,
+        file_size: content.len() as u32,
+        id,
+        flags: 0,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    };
+    index.add(&entry)
+        .map_err(|e| format!("Failed to add {} to git index: {}", path, e))
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
 }
 
 #[cfg(test)]
@@ -127,12 +627,27 @@ mod tests {
     use super::*;
     use std::fs;
     use tempfile::TempDir;
+
+    fn commit_info(command: &[String]) -> CommitInfo<'_> {
+        CommitInfo {
+            subcommand: "test",
+            feature: "default",
+            command,
+            test_dir: "build",
+            passed: true,
+            exit_code: 0,
+            duration: Duration::from_millis(1500),
+            subject: None,
+            fallback_identity: &FallbackIdentity::default,
+            written: &[],
+        }
+    }
     
     #[test]
     fn test_auto_commit_no_git_dir() {
         // Test that when .c2rust/.git doesn't exist, function returns Ok
         let temp_dir = TempDir::new().unwrap();
-        let result = auto_commit_if_modified(temp_dir.path());
+        let result = auto_commit_if_modified(temp_dir.path(), &commit_info(&[]), &StatusSnapshot::default());
         assert!(result.is_ok());
     }
     
@@ -156,19 +671,21 @@ mod tests {
         fs::write(&test_file, "test content").unwrap();
         
         // Run auto_commit_if_modified
-        let result = auto_commit_if_modified(temp_dir.path());
+        let result = auto_commit_if_modified(temp_dir.path(), &commit_info(&[]), &StatusSnapshot::default());
         assert!(result.is_ok(), "Expected auto_commit to succeed, got: {:?}", result);
         
         // Verify a commit was created
         let head = repo.head().unwrap();
         let commit = head.peel_to_commit().unwrap();
-        assert!(commit.message().unwrap().contains("Auto-commit"));
+        assert_eq!(result.unwrap(), Some(commit.id().to_string()));
+        assert!(commit.message().unwrap().starts_with("c2rust-test test [default]: PASS"));
         
         let first_commit_id = commit.id();
         
         // Run auto_commit_if_modified again without any changes
-        let result2 = auto_commit_if_modified(temp_dir.path());
+        let result2 = auto_commit_if_modified(temp_dir.path(), &commit_info(&[]), &StatusSnapshot::default());
         assert!(result2.is_ok(), "Expected second auto_commit to succeed, got: {:?}", result2);
+        assert_eq!(result2.unwrap(), None);
         
         // Verify no new commit was created
         let head2 = repo.head().unwrap();
@@ -184,21 +701,225 @@ mod tests {
         let c2rust_dir = temp_dir.path().join(".c2rust");
         fs::create_dir(&c2rust_dir).unwrap();
         
-        // Initialize a git repository WITHOUT setting user.name/user.email
+        // Initialize a git repository and corrupt its HEAD
         // This will cause git operations to fail when trying to commit
         let _repo = git2::Repository::init(&c2rust_dir).unwrap();
+        fs::write(c2rust_dir.join(".git/HEAD"), "not a reference").unwrap();
         
         // Create a test file to trigger commit attempt
         let test_file = c2rust_dir.join("test.txt");
         fs::write(&test_file, "test content").unwrap();
         
-        // Run auto_commit_if_modified - it should succeed despite git config errors
-        let result = auto_commit_if_modified(temp_dir.path());
+        // Run auto_commit_if_modified - it should succeed despite git errors
+        let result = auto_commit_if_modified(temp_dir.path(), &commit_info(&[]), &StatusSnapshot::default());
         
-        // The function should return Ok(()) even though git operations failed
+        // The function should return Ok(None) even though git operations failed
         assert!(result.is_ok(), "Expected auto_commit to succeed (non-fatal) even with git errors, got: {:?}", result);
+        assert_eq!(result.unwrap(), None);
         
         // Note: The warning message would be printed to stderr but we can't easily capture it in unit tests
         // Integration tests can verify the warning output
     }
+
+    #[test]
+    fn test_signature_prefers_git_identity() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(temp_dir.path()).unwrap();
+        // The fallback is only looked up if git has no identity
+        let fallback = || -> FallbackIdentity { panic!("fallback identity looked up") };
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        let sig = signature(&repo, &fallback).unwrap();
+        assert_eq!(sig.name(), Some("Test User"));
+        assert_eq!(sig.email(), Some("test@example.com"));
+    }
+    
+    #[test]
+    fn test_commit_message() {
+        let command = vec!["make".to_string(), "check".to_string(), "a b".to_string()];
+        let message = commit_info(&command).message();
+        assert!(message.starts_with("c2rust-test test [default]: PASS make check 'a b' (1.50s)\n\n"), "{}", message);
+        assert!(message.contains("\nC2rust-Feature: default\n"));
+        assert!(message.contains("\nC2rust-Exit-Code: 0\n"));
+        assert!(message.contains("\nC2rust-Test-Dir: build\n"));
+        assert!(message.contains("\nC2rust-Command: make check 'a b'\n"));
+
+        let failed = CommitInfo {
+            passed: false,
+            exit_code: 139,
+            subject: Some("Record crashing baseline"),
+            ..commit_info(&command)
+        };
+        let message = failed.message();
+        assert!(message.starts_with("Record crashing baseline\n\n"));
+        assert!(message.contains("\nC2rust-Status: fail\n"));
+        assert!(message.contains("\nC2rust-Exit-Code: 139\n"));
+
+        let long_command = vec!["x".repeat(100)];
+        let subject = commit_info(&long_command).message().lines().next().unwrap().to_string();
+        assert!(subject.contains(&format!("{}...", "x".repeat(SUBJECT_COMMAND_MAX - 3))));
+    }
+
+    #[test]
+    fn test_auto_commit_only_stages_touched_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let c2rust_dir = temp_dir.path().join(".c2rust");
+        fs::create_dir(&c2rust_dir).unwrap();
+        let repo = git2::Repository::init(&c2rust_dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        fs::write(c2rust_dir.join("tracked.txt"), "v1").unwrap();
+        fs::write(c2rust_dir.join("removed.txt"), "gone soon").unwrap();
+        auto_commit_if_modified(temp_dir.path(), &commit_info(&[]), &StatusSnapshot::default()).unwrap().unwrap();
+
+        // A teammate's half-finished edits, one of them staged
+        fs::write(c2rust_dir.join("draft.txt"), "wip").unwrap();
+        fs::write(c2rust_dir.join("staged.txt"), "wip").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("staged.txt")).unwrap();
+        index.write().unwrap();
+        fs::write(c2rust_dir.join("tracked.txt"), "teammate edit").unwrap();
+
+        let before = StatusSnapshot::take(temp_dir.path()).unwrap();
+
+        // What the run changes
+        fs::create_dir(c2rust_dir.join("logs")).unwrap();
+        fs::write(c2rust_dir.join("logs/run.log"), "output").unwrap();
+        fs::write(c2rust_dir.join("tracked.txt"), "v2").unwrap();
+        fs::remove_file(c2rust_dir.join("removed.txt")).unwrap();
+
+        let commit = auto_commit_if_modified(temp_dir.path(), &commit_info(&[]), &before).unwrap().unwrap();
+        let tree = repo.find_commit(git2::Oid::from_str(&commit).unwrap()).unwrap().tree().unwrap();
+        assert!(tree.get_path(Path::new("logs/run.log")).is_ok());
+        assert!(tree.get_path(Path::new("removed.txt")).is_err());
+        assert!(tree.get_path(Path::new("draft.txt")).is_err());
+        assert!(tree.get_path(Path::new("staged.txt")).is_err());
+        let tracked = tree.get_path(Path::new("tracked.txt")).unwrap().to_object(&repo).unwrap();
+        assert_eq!(tracked.as_blob().unwrap().content(), b"v2");
+
+        // The teammate's changes are still pending, the run's are not
+        let mut pending = pending_changes(temp_dir.path()).unwrap().unwrap();
+        pending.sort();
+        assert_eq!(pending, vec!["draft.txt".to_string(), "staged.txt".to_string()]);
+    }
+
+    #[test]
+    fn test_auto_commit_leaves_other_runs_output_alone() {
+        let temp_dir = TempDir::new().unwrap();
+        let c2rust_dir = temp_dir.path().join(".c2rust");
+        fs::create_dir(&c2rust_dir).unwrap();
+        git2::Repository::init(&c2rust_dir).unwrap();
+        // An earlier run's uncommitted log, and a concurrent run's log in progress
+        for path in ["fa/test-logs/0.log", "fc/test-logs/3.log"] {
+            fs::create_dir_all(c2rust_dir.join(path).parent().unwrap()).unwrap();
+            fs::write(c2rust_dir.join(path), "output").unwrap();
+        }
+        let before = StatusSnapshot::take(temp_dir.path()).unwrap();
+        fs::write(c2rust_dir.join("fc/test-logs/3.log"), "more output").unwrap();
+
+        // This run's output and configuration, and another concurrent run's log
+        for path in ["fa/test-logs/1.log", "fa/golden/stdout.txt", "fb/test-logs/2.log"] {
+            fs::create_dir_all(c2rust_dir.join(path).parent().unwrap()).unwrap();
+            fs::write(c2rust_dir.join(path), "output").unwrap();
+        }
+        fs::write(c2rust_dir.join("config.toml"), "[fa]").unwrap();
+
+        let written = [c2rust_dir.join("fa/test-logs/1.log"), c2rust_dir.join("fa/golden")];
+        let info = CommitInfo { written: &written, ..commit_info(&[]) };
+        auto_commit_if_modified(temp_dir.path(), &info, &before).unwrap().unwrap();
+        assert_eq!(
+            pending_changes(temp_dir.path()).unwrap().unwrap(),
+            vec!["fb/test-logs/2.log".to_string(), "fc/test-logs/3.log".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let c2rust_dir = temp_dir.path().join(".c2rust");
+        fs::create_dir(&c2rust_dir).unwrap();
+        let repo = git2::Repository::init(&c2rust_dir).unwrap();
+        assert!(history(temp_dir.path(), None, None).unwrap().is_empty());
+
+        let command = vec!["make".to_string(), "check".to_string()];
+        let runs = [("f1", true, 0), ("f2", false, 2), ("f1", false, 139)];
+        for (i, (feature, passed, exit_code)) in runs.into_iter().enumerate() {
+            fs::write(c2rust_dir.join("log.txt"), i.to_string()).unwrap();
+            let info = CommitInfo { feature, passed, exit_code, ..commit_info(&command) };
+            auto_commit_if_modified(temp_dir.path(), &info, &StatusSnapshot::default()).unwrap().unwrap();
+        }
+
+        // A commit without trailers is not a test run
+        let sig = git2::Signature::now("Someone", "someone@example.com").unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Manual edit", &head.tree().unwrap(), &[&head]).unwrap();
+
+        let entries = history(temp_dir.path(), None, None).unwrap();
+        assert_eq!(entries.len(), 3);
+        let f1 = history(temp_dir.path(), Some("f1"), None).unwrap();
+        assert_eq!(f1.len(), 2);
+        assert_eq!(f1[0].status, "fail");
+        assert_eq!(f1[0].exit_code, Some(139));
+        assert_eq!(f1[0].command, "make check");
+        assert_eq!(f1[0].duration.as_deref(), Some("1.50s"));
+        assert_eq!(f1[1].status, "pass");
+
+        assert_eq!(history(temp_dir.path(), Some("f1"), Some(1)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_commit_policy_from_str() {
+        assert_eq!("always".parse::<CommitPolicy>(), Ok(CommitPolicy::Always));
+        assert_eq!("on-success".parse::<CommitPolicy>(), Ok(CommitPolicy::OnSuccess));
+        assert_eq!("never\n".parse::<CommitPolicy>(), Ok(CommitPolicy::Never));
+        assert!("sometimes".parse::<CommitPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_tag_and_list_baselines() {
+        let temp_dir = TempDir::new().unwrap();
+        let c2rust_dir = temp_dir.path().join(".c2rust");
+        fs::create_dir(&c2rust_dir).unwrap();
+        let repo = git2::Repository::init(&c2rust_dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        fs::write(c2rust_dir.join("test.txt"), "1").unwrap();
+        let commit = auto_commit_if_modified(temp_dir.path(), &commit_info(&[]), &StatusSnapshot::default()).unwrap().unwrap();
+
+        let name = tag_baseline(temp_dir.path(), &commit, "default", "20261018-120000", false, &FallbackIdentity::default).unwrap();
+        assert_eq!(name, "test-pass/default/20261018-120000");
+        tag_baseline(temp_dir.path(), &commit, "default/sub", "20261018-120001", true, &FallbackIdentity::default).unwrap();
+        // Tags are never overwritten
+        assert!(tag_baseline(temp_dir.path(), &commit, "default", "20261018-120000", false, &FallbackIdentity::default).is_err());
+
+        let all = list_baselines(temp_dir.path(), None).unwrap();
+        assert_eq!(all.len(), 2);
+        assert!(all.iter().all(|baseline| baseline.commit == commit));
+
+        let default = list_baselines(temp_dir.path(), Some("default")).unwrap();
+        assert_eq!(default.len(), 1);
+        assert_eq!(default[0].name, "test-pass/default/20261018-120000");
+        assert!(default[0].summary.starts_with("c2rust-test test [default]: PASS"));
+    }
+
+    #[test]
+    fn test_pending_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(pending_changes(temp_dir.path()).unwrap(), None);
+
+        let c2rust_dir = temp_dir.path().join(".c2rust");
+        fs::create_dir(&c2rust_dir).unwrap();
+        git2::Repository::init(&c2rust_dir).unwrap();
+        assert_eq!(pending_changes(temp_dir.path()).unwrap(), Some(Vec::new()));
+
+        fs::write(c2rust_dir.join("config.toml"), "x").unwrap();
+        fs::write(c2rust_dir.join(crate::lock::LOCK_FILE), "").unwrap();
+        assert_eq!(pending_changes(temp_dir.path()).unwrap(), Some(vec!["config.toml".to_string()]));
+    }
 }
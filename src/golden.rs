@@ -0,0 +1,84 @@
+use crate::compare::{self, Divergence};
+use crate::normalize::Normalizer;
+use std::path::{Path, PathBuf};
+
+/// Files holding the golden output, inside the feature's golden directory
+const STDOUT_FILE: &str = "stdout.txt";
+const STDERR_FILE: &str = "stderr.txt";
+
+/// Labels used when printing a golden mismatch
+pub const LABELS: compare::Labels = ("Golden", "Actual");
+
+/// Directory of a feature's golden output: `<project_root>/.c2rust/<feature>/golden`
+pub fn golden_dir(project_root: &Path, feature: &str) -> PathBuf {
+    project_root.join(".c2rust").join(feature).join("golden")
+}
+
+/// Store the output of a passing run as the feature's golden output.
+/// Returns the directory it was written to.
+pub fn record(project_root: &Path, feature: &str, stdout: &str, stderr: &str) -> std::io::Result<PathBuf> {
+    let dir = golden_dir(project_root, feature);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(STDOUT_FILE), stdout)?;
+    std::fs::write(dir.join(STDERR_FILE), stderr)?;
+    Ok(dir)
+}
+
+/// Compare a run's output with the feature's golden output, after normalizing both.
+/// Returns the first difference in each stream; an empty list means the output matches.
+pub fn check(
+    project_root: &Path,
+    feature: &str,
+    stdout: &str,
+    stderr: &str,
+    normalizer: &Normalizer,
+) -> std::result::Result<Vec<Divergence>, String> {
+    let dir = golden_dir(project_root, feature);
+    let read = |file: &str| {
+        let path = dir.join(file);
+        std::fs::read_to_string(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!(
+                "no golden output at {}; record it with --record-golden first",
+                path.display()
+            ),
+            _ => format!("failed to read {}: {}", path.display(), e),
+        })
+    };
+    // The golden files keep the raw output, so changed rules apply to them too
+    let golden_stdout = normalizer.apply(&read(STDOUT_FILE)?);
+    let golden_stderr = normalizer.apply(&read(STDERR_FILE)?);
+    let stdout = normalizer.apply(stdout);
+    let stderr = normalizer.apply(stderr);
+
+    Ok(compare::first_difference("stdout", &golden_stdout, &stdout)
+        .into_iter()
+        .chain(compare::first_difference("stderr", &golden_stderr, &stderr))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_check() {
+        let temp_dir = TempDir::new().unwrap();
+        let none = Normalizer::default();
+        assert!(check(temp_dir.path(), "f1", "", "", &none).unwrap_err().contains("--record-golden"));
+
+        let dir = record(temp_dir.path(), "f1", "1 + 1 = 2\n", "").unwrap();
+        assert_eq!(dir, temp_dir.path().join(".c2rust/f1/golden"));
+        assert_eq!(std::fs::read_to_string(dir.join("stdout.txt")).unwrap(), "1 + 1 = 2\n");
+
+        assert!(check(temp_dir.path(), "f1", "1 + 1 = 2\n", "", &none).unwrap().is_empty());
+
+        let divergences = check(temp_dir.path(), "f1", "1 + 1 = 3\n", "warning\n", &none).unwrap();
+        assert_eq!(divergences.len(), 2);
+        assert_eq!(divergences[0].summary(LABELS), "stdout differs at line 1");
+        assert_eq!(divergences[1].summary(LABELS), "stderr differs at line 1");
+
+        let digits = Normalizer::parse(r#"[{"pattern": "\\d", "replace": "N"}]"#).unwrap();
+        assert!(check(temp_dir.path(), "f1", "1 + 1 = 3\n", "", &digits).unwrap().is_empty());
+    }
+}
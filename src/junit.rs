@@ -0,0 +1,177 @@
+use crate::results::{self, TestCase, TestStatus};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Collect the test cases from every JUnit XML file matching `pattern`.
+///
+/// Relative patterns are resolved against `base_dir`, whose own name is matched
+/// literally even if it contains glob characters. Files last modified
+/// before `since` were left over from an earlier run and are skipped.
+/// Problems with individual files are reported as warnings; they never fail the run.
+pub fn collect(pattern: &str, base_dir: &Path, since: SystemTime) -> Vec<TestCase> {
+    let full_pattern = if Path::new(pattern).is_absolute() {
+        PathBuf::from(pattern)
+    } else {
+        PathBuf::from(glob::Pattern::escape(&base_dir.to_string_lossy())).join(pattern)
+    };
+
+    let paths = match glob::glob(&full_pattern.to_string_lossy()) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("Warning: invalid JUnit pattern '{}': {}", pattern, e);
+            return Vec::new();
+        }
+    };
+
+    let mut cases = Vec::new();
+    let mut matched = false;
+    for path in paths.flatten() {
+        matched = true;
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified());
+        if matches!(modified, Ok(modified) if !results::written_since(modified, since)) {
+            eprintln!("Warning: ignoring stale JUnit file {} (not written by this run)", path.display());
+            continue;
+        }
+        match parse_file(&path) {
+            Ok(mut parsed) => cases.append(&mut parsed),
+            Err(e) => eprintln!("Warning: failed to parse JUnit file {}: {}", path.display(), e),
+        }
+    }
+
+    if !matched {
+        eprintln!("Warning: no JUnit files matched '{}'", pattern);
+    }
+
+    cases
+}
+
+/// Parse a single JUnit XML file
+pub fn parse_file(path: &Path) -> std::result::Result<Vec<TestCase>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    parse_str(&content)
+}
+
+/// Parse JUnit XML content. Both `<testsuites>` and bare `<testsuite>` roots are accepted.
+pub fn parse_str(content: &str) -> std::result::Result<Vec<TestCase>, String> {
+    let doc = roxmltree::Document::parse(content).map_err(|e| e.to_string())?;
+
+    let cases = doc
+        .descendants()
+        .filter(|node| node.has_tag_name("testcase"))
+        .map(|node| {
+            let name = node.attribute("name").unwrap_or("<unnamed>");
+            let name = match node.attribute("classname").filter(|c| !c.is_empty()) {
+                Some(classname) => format!("{}.{}", classname, name),
+                None => name.to_string(),
+            };
+
+            let outcome = node
+                .children()
+                .find(|child| child.has_tag_name("failure") || child.has_tag_name("error") || child.has_tag_name("skipped"));
+            let status = match outcome {
+                Some(child) if child.has_tag_name("skipped") => TestStatus::Skipped,
+                Some(_) => TestStatus::Failed,
+                None => TestStatus::Passed,
+            };
+            let message = outcome.and_then(|child| child.attribute("message")).map(str::to_string);
+
+            let duration = node
+                .attribute("time")
+                .and_then(|time| time.parse::<f64>().ok())
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+
+            TestCase { name, status, duration, message }
+        })
+        .collect();
+
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="list" tests="4">
+    <testcase classname="list" name="push" time="0.012"/>
+    <testcase classname="list" name="pop" time="0.5">
+      <failure message="expected 3, got 4">assertion failed</failure>
+    </testcase>
+    <testcase classname="list" name="sort">
+      <error message="segfault"/>
+    </testcase>
+    <testcase name="huge">
+      <skipped message="too slow"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#;
+
+    #[test]
+    fn test_parse_str() {
+        let cases = parse_str(SAMPLE).unwrap();
+        assert_eq!(cases.len(), 4);
+
+        assert_eq!(cases[0].name, "list.push");
+        assert_eq!(cases[0].status, TestStatus::Passed);
+        assert_eq!(cases[0].duration, Some(Duration::from_millis(12)));
+
+        assert_eq!(cases[1].status, TestStatus::Failed);
+        assert_eq!(cases[1].message.as_deref(), Some("expected 3, got 4"));
+
+        assert_eq!(cases[2].status, TestStatus::Failed);
+
+        assert_eq!(cases[3].name, "huge");
+        assert_eq!(cases[3].status, TestStatus::Skipped);
+    }
+
+    #[test]
+    fn test_parse_str_invalid() {
+        assert!(parse_str("<testsuite>").is_err());
+    }
+
+    #[test]
+    fn test_collect_glob() {
+        let temp_dir = TempDir::new().unwrap();
+        let since = SystemTime::now() - Duration::from_secs(60);
+        std::fs::create_dir(temp_dir.path().join("reports")).unwrap();
+        std::fs::write(temp_dir.path().join("reports/a.xml"), SAMPLE).unwrap();
+        std::fs::write(
+            temp_dir.path().join("reports/b.xml"),
+            r#"<testsuite><testcase name="extra"/></testsuite>"#,
+        )
+        .unwrap();
+
+        let cases = collect("reports/*.xml", temp_dir.path(), since);
+        assert_eq!(cases.len(), 5);
+    }
+
+    #[test]
+    fn test_collect_in_directory_with_glob_characters() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_dir = temp_dir.path().join("proj[1]*?");
+        std::fs::create_dir(&base_dir).unwrap();
+        std::fs::write(base_dir.join("report.xml"), SAMPLE).unwrap();
+
+        let since = SystemTime::now() - Duration::from_secs(60);
+        assert_eq!(collect("*.xml", &base_dir, since).len(), 4);
+    }
+
+    #[test]
+    fn test_collect_skips_stale_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("report.xml");
+        std::fs::write(&path, SAMPLE).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        let since = SystemTime::now();
+
+        // A coarse file clock may date a fresh file slightly before the run started
+        file.set_modified(since - Duration::from_millis(500)).unwrap();
+        assert_eq!(collect("report.xml", temp_dir.path(), since).len(), 4);
+
+        file.set_modified(since - Duration::from_secs(60)).unwrap();
+        assert!(collect("report.xml", temp_dir.path(), since).is_empty());
+    }
+}
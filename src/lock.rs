@@ -0,0 +1,167 @@
+use crate::error::{Error, Result};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Lock file inside `.c2rust`, relative to that directory
+pub const LOCK_FILE: &str = ".lock";
+
+/// How often a blocked invocation retries the lock
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Exclusive advisory lock on `<project_root>/.c2rust/.lock`.
+///
+/// Held while the configuration is saved and `.c2rust` is committed, so that
+/// concurrent c2rust-test invocations in the same project do not interleave
+/// their c2rust-config writes or git index updates. Released when dropped.
+pub struct C2rustLock {
+    _file: File,
+}
+
+impl C2rustLock {
+    /// Take the lock, waiting at most `timeout` for another invocation to release it.
+    /// `None` waits indefinitely; a zero timeout fails immediately if the lock is held.
+    pub fn acquire(project_root: &Path, timeout: Option<Duration>) -> Result<Self> {
+        let path = lock_path(project_root);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|e| Error::LockFailed(format!("cannot open {}: {}", path.display(), e)))?;
+        if let Err(e) = exclude_from_git(project_root) {
+            eprintln!("Warning: failed to exclude {} from the .c2rust repository: {}", LOCK_FILE, e);
+        }
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut waiting = false;
+        while !try_lock(&file).map_err(|e| Error::LockFailed(format!("cannot lock {}: {}", path.display(), e)))? {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(Error::LockFailed(format!(
+                    "{} is held by another c2rust-test invocation",
+                    path.display()
+                )));
+            }
+            if !waiting {
+                println!("Waiting for another c2rust-test invocation to release {} ...", path.display());
+                waiting = true;
+            }
+            let sleep = match deadline {
+                Some(deadline) => LOCK_POLL_INTERVAL.min(deadline.saturating_duration_since(Instant::now())),
+                None => LOCK_POLL_INTERVAL,
+            };
+            thread::sleep(sleep);
+        }
+
+        Ok(C2rustLock { _file: file })
+    }
+}
+
+/// Path of the lock file for a project
+pub fn lock_path(project_root: &Path) -> PathBuf {
+    project_root.join(".c2rust").join(LOCK_FILE)
+}
+
+/// Keep the lock file out of `git status` and `git add -A` in the .c2rust
+/// repository by listing it in `.git/info/exclude`, once
+fn exclude_from_git(project_root: &Path) -> std::io::Result<()> {
+    let git_dir = project_root.join(".c2rust").join(".git");
+    if !git_dir.is_dir() {
+        return Ok(());
+    }
+    let pattern = format!("/{}", LOCK_FILE);
+    let exclude = git_dir.join("info").join("exclude");
+    let content = match std::fs::read_to_string(&exclude) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    if content.lines().any(|line| line.trim() == pattern) {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(git_dir.join("info"))?;
+    let mut file = OpenOptions::new().create(true).append(true).open(&exclude)?;
+    let separator = if content.is_empty() || content.ends_with('\n') { "" } else { "\n" };
+    writeln!(file, "{}{}", separator, pattern)
+}
+
+/// Try to take an exclusive flock without blocking; `Ok(false)` if it is held elsewhere
+#[cfg(unix)]
+fn try_lock(file: &File) -> std::io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: flock(2) only operates on the file descriptor, which stays open
+    // for the lifetime of `file`
+    let ret = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if ret == 0 {
+        return Ok(true);
+    }
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(not(unix))]
+fn try_lock(_file: &File) -> std::io::Result<bool> {
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock_is_exclusive() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock = C2rustLock::acquire(temp_dir.path(), Some(Duration::ZERO)).unwrap();
+        assert!(lock_path(temp_dir.path()).exists());
+
+        // flock locks belong to the open file, so a second open conflicts even in-process
+        let result = C2rustLock::acquire(temp_dir.path(), Some(Duration::ZERO));
+        assert!(matches!(result, Err(Error::LockFailed(_))));
+
+        drop(lock);
+        assert!(C2rustLock::acquire(temp_dir.path(), Some(Duration::ZERO)).is_ok());
+    }
+
+    #[test]
+    fn test_lock_file_is_excluded_from_git() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(temp_dir.path().join(".c2rust")).unwrap();
+        std::fs::write(repo.path().join("info/exclude"), "# existing\n*.tmp").unwrap();
+
+        drop(C2rustLock::acquire(temp_dir.path(), None).unwrap());
+        drop(C2rustLock::acquire(temp_dir.path(), None).unwrap());
+
+        let exclude = std::fs::read_to_string(repo.path().join("info/exclude")).unwrap();
+        assert_eq!(exclude, "# existing\n*.tmp\n/.lock\n");
+        assert!(repo.status_should_ignore(Path::new(LOCK_FILE)).unwrap());
+    }
+
+    #[test]
+    fn test_lock_waits_for_release() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock = C2rustLock::acquire(temp_dir.path(), None).unwrap();
+
+        let start = Instant::now();
+        let result = C2rustLock::acquire(temp_dir.path(), Some(Duration::from_millis(300)));
+        assert!(result.is_err());
+        assert!(start.elapsed() >= Duration::from_millis(300));
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            drop(lock);
+        });
+        assert!(C2rustLock::acquire(temp_dir.path(), Some(Duration::from_secs(10))).is_ok());
+        releaser.join().unwrap();
+    }
+}
@@ -1,11 +1,24 @@
+mod compare;
 mod config_helper;
+mod ctest;
 mod error;
 mod executor;
 mod git_helper;
+mod golden;
+mod junit;
+mod lock;
+mod normalize;
+mod regression;
+mod report;
+mod results;
+mod tap;
+mod xfail;
 
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use error::Result;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "c2rust-test")]
@@ -19,20 +32,736 @@ struct Cli {
 enum Commands {
     /// Execute test command
     Test(CommandArgs),
+    /// Re-run the test command saved by a previous 'test' invocation
+    Run(RunArgs),
+    /// Run the saved test command against the C build and the Rust build and
+    /// compare their exit codes, stdout and stderr
+    Diff(DiffArgs),
+    /// List past test runs recorded in the .c2rust repository, newest first
+    History(HistoryArgs),
+    /// Manage the known-good baselines tagged in the .c2rust repository
+    Baseline {
+        #[command(subcommand)]
+        command: BaselineCommand,
+    },
+    /// Manage the feature's list of tests that are expected to fail (xfail)
+    Xfail {
+        #[command(subcommand)]
+        command: XfailCommand,
+    },
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Optional feature name (default: "default")
+    #[arg(long, value_parser = parse_feature)]
+    feature: Option<String>,
+
+    #[command(flatten)]
+    commit: CommitArgs,
+}
+
+#[derive(Args)]
+struct HistoryArgs {
+    /// Only list the runs of this feature
+    #[arg(long, value_parser = parse_feature)]
+    feature: Option<String>,
+
+    /// Show at most this many runs
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+}
+
+#[derive(Subcommand)]
+enum BaselineCommand {
+    /// List the test-pass/<feature>/<timestamp> tags, oldest first
+    List {
+        /// Only list the baselines of this feature
+        #[arg(long, value_parser = parse_feature)]
+        feature: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum XfailCommand {
+    /// Add tests to the xfail list; their failures then count as OK
+    Add(XfailArgs),
+    /// Remove tests from the xfail list, e.g. once they are reported as XPASS
+    Remove(XfailArgs),
+    /// List the expected failures of a feature
+    List {
+        /// Optional feature name (default: "default")
+        #[arg(long, value_parser = parse_feature)]
+        feature: Option<String>,
+    },
+}
+
+#[derive(Args)]
+struct XfailArgs {
+    /// Optional feature name (default: "default")
+    #[arg(long, value_parser = parse_feature)]
+    feature: Option<String>,
+
+    /// Test names, as reported by the result parsers
+    #[arg(required = true, value_name = "TEST")]
+    tests: Vec<String>,
 }
 
 #[derive(Args)]
 struct CommandArgs {
     /// Optional feature name (default: "default")
-    #[arg(long)]
+    #[arg(long, value_parser = parse_feature)]
     feature: Option<String>,
 
+    /// Kill the test command and all processes it started if it runs longer
+    /// than this (e.g. 90s, 10m, 1h; a bare number means seconds)
+    #[arg(long, value_name = "DURATION", value_parser = config_helper::parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Set an environment variable for the test command (repeatable);
+    /// saved as test.env so 'run' reproduces it
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_assignment)]
+    env: Vec<(String, String)>,
+
+    /// Run the test command through $SHELL (or /bin/sh) with -c, so pipelines
+    /// and compound commands work; saved as test.shell for replays
+    #[arg(long)]
+    shell: bool,
+
+    /// Save the configuration and auto-commit even if the test command fails,
+    /// recording test.last_status=fail and the exit code
+    #[arg(long)]
+    record_on_failure: bool,
+
+    /// Show what would be executed, saved and committed without doing any of it
+    #[arg(long)]
+    dry_run: bool,
+
+    #[command(flatten)]
+    results: ResultArgs,
+
+    #[command(flatten)]
+    commit: CommitArgs,
+
     /// Test command to execute - use after '--' separator
     /// Example: c2rust-test test -- make test
     #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true, value_name = "TEST_CMD")]
     test_cmd: Vec<String>,
 }
 
+#[derive(Args)]
+struct RunArgs {
+    /// Optional feature name (default: "default")
+    #[arg(long, value_parser = parse_feature)]
+    feature: Option<String>,
+
+    #[command(flatten)]
+    results: ResultArgs,
+
+    #[command(flatten)]
+    commit: CommitArgs,
+}
+
+/// Options for the auto-commit in .c2rust, shared by 'test', 'run' and 'diff'
+#[derive(Args, Default)]
+struct CommitArgs {
+    /// Subject line of the auto-commit, replacing the generated summary
+    /// (the C2rust-* trailers are still added)
+    #[arg(long, value_name = "TEXT")]
+    message: Option<String>,
+
+    /// Do not auto-commit .c2rust changes (also set by C2RUST_TEST_NO_COMMIT);
+    /// overrides test.commit_policy
+    #[arg(long)]
+    no_commit: bool,
+
+    /// After a passing run, tag the auto-commit as test-pass/<feature>/<timestamp>
+    /// (lightweight unless 'annotated' is given)
+    #[arg(long, value_enum, value_name = "KIND", num_args = 0..=1, default_missing_value = "lightweight")]
+    tag_baseline: Option<TagKind>,
+
+    /// How long to wait for another c2rust-test invocation to release .c2rust/.lock
+    /// before saving and committing (default: wait indefinitely; 0 fails immediately)
+    #[arg(long, value_name = "DURATION", value_parser = config_helper::parse_duration)]
+    lock_timeout: Option<Duration>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TagKind {
+    Lightweight,
+    Annotated,
+}
+
+/// Options for collecting and reporting per-test results, shared by 'test' and 'run'
+#[derive(Args, Default)]
+struct ResultArgs {
+    /// Write a JSON report of the run to this file
+    #[arg(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    /// JUnit XML file(s) written by the test command; relative paths and glob
+    /// patterns are resolved against the test directory
+    #[arg(long, value_name = "PATH|GLOB")]
+    junit: Option<String>,
+
+    /// Result format of the test command (ctest is detected automatically)
+    #[arg(long, value_enum)]
+    format: Option<ResultFormat>,
+
+    /// Store the stdout/stderr of a passing run as the feature's golden output
+    /// in .c2rust/<feature>/golden/
+    #[arg(long, conflicts_with = "check_golden")]
+    record_golden: bool,
+
+    /// Fail the run if its stdout/stderr differ from the recorded golden output,
+    /// even if the command exits 0
+    #[arg(long)]
+    check_golden: bool,
+
+    /// What fails the run: any failure (default), or only regressions, i.e.
+    /// tests that passed in the previous run and fail now
+    #[arg(long, value_enum, value_name = "WHAT")]
+    fail_on: Option<FailOn>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FailOn {
+    /// A non-zero exit code or any failing test
+    Any,
+    /// A test that passed in the previous run fails; needs per-test results
+    Regressions,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ResultFormat {
+    /// Test Anything Protocol on stdout; a TAP failure fails the run even if
+    /// the command exits 0
+    Tap,
+    /// CTest: results are read from ctest's JUnit output or LastTest.log
+    Ctest,
+}
+
+/// A resolved test invocation, shared by the 'test' and 'run' subcommands
+struct TestRun<'a> {
+    /// Directory c2rust-test was started in
+    invoked_from: PathBuf,
+    project_root: PathBuf,
+    /// Absolute directory the command runs in
+    test_dir: PathBuf,
+    /// `test_dir` relative to the project root, as stored in test.dir
+    test_dir_relative: String,
+    feature: &'a str,
+    test_cmd: Vec<String>,
+    timeout: Option<Duration>,
+    env: Vec<(String, String)>,
+    /// Interpreter for shell mode
+    shell: Option<String>,
+    results: ResultArgs,
+    commit: CommitArgs,
+    /// Keep the command's output in memory, for comparing runs
+    capture: Option<executor::CapturedOutput>,
+    /// Compare per-test results with the previous run and store them for the next one
+    track_results: bool,
+}
+
+impl<'a> TestRun<'a> {
+    /// A run of a saved configuration; its test.dir is relative to the project root
+    fn from_config(
+        invoked_from: PathBuf,
+        project_root: PathBuf,
+        feature: &'a str,
+        test_config: config_helper::TestConfig,
+        results: ResultArgs,
+        commit: CommitArgs,
+    ) -> Self {
+        TestRun {
+            invoked_from,
+            test_dir: project_root.join(&test_config.dir),
+            project_root,
+            test_dir_relative: test_config.dir,
+            feature,
+            test_cmd: test_config.argv,
+            timeout: test_config.timeout,
+            env: test_config.env,
+            shell: test_config.shell,
+            results,
+            commit,
+            capture: None,
+            track_results: true,
+        }
+    }
+
+    fn print_header(&self) {
+        println!("=== c2rust-test ===");
+        println!("Project root: {}", self.project_root.display());
+        println!("Test directory (relative): {}", self.test_dir_relative);
+        println!("Feature: {}", self.feature);
+        println!("Command: {}", shell_words::join(&self.test_cmd));
+        if let Some(timeout) = self.timeout {
+            println!("Timeout: {}", config_helper::format_duration(timeout));
+        }
+        for (key, value) in &self.env {
+            println!("Environment: {}={}", key, value);
+        }
+        if let Some(shell) = &self.shell {
+            println!("Shell: {}", shell);
+        }
+        println!();
+    }
+
+    /// The configuration recorded for this run
+    fn config(&self) -> config_helper::TestConfig {
+        config_helper::TestConfig {
+            dir: self.test_dir_relative.clone(),
+            argv: self.test_cmd.clone(),
+            timeout: self.timeout,
+            env: self.env.clone(),
+            shell: self.shell.clone(),
+            last_run: None,
+        }
+    }
+
+    /// Print what `execute`, `save_config` and the auto-commit would do,
+    /// without running the command or writing anything
+    fn dry_run(&self) -> Result<()> {
+        let argv = match &self.shell {
+            Some(shell) => {
+                let script = executor::shell_script(&self.test_cmd)
+                    .map_err(|e| error::Error::CommandExecutionFailed(e, None))?;
+                executor::shell_command(shell, script)
+            }
+            None => self.test_cmd.clone(),
+        };
+
+        println!("Dry run: nothing will be executed or written.");
+        println!();
+        println!("Would execute: {}", shell_words::join(&argv));
+        println!("Argv: {:?}", argv);
+        println!("Working directory: {}", self.test_dir.display());
+        println!("Log file: {}", run_log_path(&self.project_root, self.feature).display());
+        let golden_dir = golden::golden_dir(&self.project_root, self.feature);
+        if self.results.record_golden {
+            println!("Would record golden output in {}", golden_dir.display());
+        }
+        if self.results.check_golden {
+            println!("Would compare output with the golden output in {}", golden_dir.display());
+        }
+        println!();
+
+        // Shown as it would be saved by a passing run
+        let mut config = self.config();
+        config.last_run = Some(config_helper::LastRun { passed: true, exit_code: 0 });
+        println!("Would save configuration (in {}):", self.project_root.display());
+        for invocation in config_helper::save_invocations(&config, Some(self.feature)) {
+            println!("  {}", shell_words::join(&invocation));
+        }
+        println!();
+
+        let (policy, source) = self.commit_policy();
+        match git_helper::pending_changes(&self.project_root) {
+            _ if policy == git_helper::CommitPolicy::Never => println!("Auto-commit: disabled by {}", source),
+            Ok(None) => println!("Auto-commit: skipped, {} has no git repository", self.project_root.join(".c2rust").display()),
+            Ok(Some(paths)) if paths.is_empty() => {
+                println!("Auto-commit: .c2rust has no pending changes (the new config and log would be committed)")
+            }
+            Ok(Some(paths)) => {
+                println!("Auto-commit: only the new config and log would be committed; .c2rust has {} pending change(s) that would be left alone:", paths.len());
+                for path in paths {
+                    println!("  {}", path);
+                }
+            }
+            Err(e) => eprintln!("Warning: failed to inspect .c2rust: {}", e),
+        }
+
+        Ok(())
+    }
+
+    /// Execute the test command in its directory, keeping a copy of its output
+    /// in the feature's log directory so it is committed with the config
+    fn execute(&self) -> RunOutcome {
+        let log_path = run_log_path(&self.project_root, self.feature);
+        let format = self.results.format.or_else(|| {
+            ctest::is_ctest_command(&self.test_cmd).then_some(ResultFormat::Ctest)
+        });
+        let expected_failures = xfail::load(&self.project_root, self.feature).unwrap_or_else(|e| {
+            eprintln!(
+                "Warning: ignoring {}: {}",
+                xfail::xfail_path(&self.project_root, self.feature).display(),
+                e
+            );
+            xfail::XfailList::new()
+        });
+        let tap = (format == Some(ResultFormat::Tap))
+            .then(|| Arc::new(Mutex::new(tap::TapParser::expecting_failures(expected_failures.clone()))));
+        let golden = self.results.record_golden || self.results.check_golden;
+        let capture = self.capture.clone().or_else(|| golden.then(executor::CapturedOutput::default));
+        // Only the .c2rust changes made from here on are auto-committed
+        let status_before = git_helper::StatusSnapshot::take(&self.project_root).unwrap_or_else(|e| {
+            eprintln!("Warning: failed to inspect .c2rust, all its changes will be committed: {}", e);
+            git_helper::StatusSnapshot::default()
+        });
+        let exec_options = executor::ExecOptions {
+            log_path: Some(&log_path),
+            timeout: self.timeout,
+            tap: tap.clone(),
+            env: &self.env,
+            shell: self.shell.as_deref(),
+            capture: capture.clone(),
+        };
+        let started_at = chrono::Local::now();
+        let result = executor::execute_command(&self.test_dir, &self.test_cmd, &exec_options);
+        let finished_at = chrono::Local::now();
+        let command_status = executor::CommandStatus::of(&result);
+
+        // Per-test results are most useful when the command failed, so they are
+        // collected before the command's status is propagated
+        let mut cases = Vec::new();
+        let mut failures = Vec::new();
+        let mut incomplete = Vec::new();
+        if let Some(parser) = tap {
+            let summary = parser.lock().unwrap_or_else(|e| e.into_inner()).summary();
+            summary.print();
+            if !summary.is_success() {
+                failures.push(summary.problems.join("; "));
+            }
+            incomplete = summary.incomplete;
+            cases.extend(summary.cases);
+        }
+        if format == Some(ResultFormat::Ctest) {
+            if let Some(mut ctest_cases) = ctest::collect(&self.test_cmd, &self.test_dir, started_at.into()) {
+                xfail::apply(&expected_failures, &mut ctest_cases);
+                results::print_compact_summary("CTest", &ctest_cases);
+                cases.extend(ctest_cases);
+            }
+        }
+        if let Some(pattern) = &self.results.junit {
+            let mut junit_cases = junit::collect(pattern, &self.test_dir, started_at.into());
+            xfail::apply(&expected_failures, &mut junit_cases);
+            results::print_summary("JUnit", &junit_cases);
+            cases.extend(junit_cases);
+        }
+
+        let xpasses: Vec<&str> = cases
+            .iter()
+            .filter(|case| case.status == results::TestStatus::UnexpectedPass)
+            .map(|case| case.name.as_str())
+            .collect();
+        if !xpasses.is_empty() {
+            println!("XPASS: {} test(s) in the xfail list now pass: {}", xpasses.len(), xpasses.join(", "));
+            println!(
+                "Remove them with: c2rust-test xfail remove --feature {} {}",
+                self.feature,
+                shell_words::join(&xpasses)
+            );
+            println!();
+        }
+
+        // When every failing test is an expected failure, the command's failing
+        // exit status only reflects those and is ignored
+        let mut ignored_failure = None;
+        let mut result = result;
+        let command_failed = matches!(result, Err(error::Error::CommandExecutionFailed(_, Some(_))));
+        if command_failed
+            && results::count(&cases, results::TestStatus::ExpectedFailure) > 0
+            && results::count(&cases, results::TestStatus::Failed) == 0
+        {
+            ignored_failure = Some("all failing tests are expected failures (xfail)");
+            result = Ok(());
+        }
+
+        // With --fail-on regressions, only tests that passed in the previous run
+        // can fail the run; the exit code and other failing tests are ignored.
+        // A TAP stream that ended early still fails it, as some tests never ran.
+        let mut written = vec![log_path.clone()];
+        let regressions = self.track_results(&cases);
+        if regressions.is_some() {
+            written.push(regression::results_path(&self.project_root, self.feature));
+        }
+        if self.results.fail_on == Some(FailOn::Regressions) {
+            match regressions {
+                Some(regressions) => {
+                    failures = incomplete;
+                    let names = |kind: regression::Change| -> Vec<&str> {
+                        regressions
+                            .iter()
+                            .filter(|(_, change)| *change == kind)
+                            .map(|(name, _)| name.as_str())
+                            .collect()
+                    };
+                    let failing = names(regression::Change::NewlyFailing);
+                    if !failing.is_empty() {
+                        failures.push(format!(
+                            "{} previously passing test(s) now fail: {}",
+                            failing.len(),
+                            failing.join(", ")
+                        ));
+                    }
+                    let missing = names(regression::Change::Missing);
+                    if !missing.is_empty() {
+                        failures.push(format!(
+                            "{} previously passing test(s) did not run: {}",
+                            missing.len(),
+                            missing.join(", ")
+                        ));
+                    }
+                    if command_failed && failures.is_empty() {
+                        ignored_failure = Some("no previously passing test fails (--fail-on regressions)");
+                    }
+                    if command_failed {
+                        result = Ok(());
+                    }
+                }
+                None => println!("No per-test results to compare: --fail-on regressions falls back to the exit code."),
+            }
+        }
+
+        if self.results.check_golden {
+            let capture = capture.as_ref().expect("output is captured for golden checks");
+            let checked = normalize::load(Some(self.feature), &self.project_root)
+                .map_err(|e| e.to_string())
+                .and_then(|normalizer| {
+                    golden::check(&self.project_root, self.feature, &capture.stdout(), &capture.stderr(), &normalizer)
+                });
+            match checked {
+                Ok(divergences) if divergences.is_empty() => println!("✓ Output matches the golden output."),
+                Ok(divergences) => {
+                    for divergence in &divergences {
+                        divergence.print(golden::LABELS);
+                    }
+                    let summaries: Vec<String> =
+                        divergences.iter().map(|divergence| divergence.summary(golden::LABELS)).collect();
+                    failures.push(format!("output differs from the golden output: {}", summaries.join("; ")));
+                }
+                Err(e) => failures.push(e),
+            }
+            println!();
+        }
+
+        // A failing exit status takes precedence over a failure reported only
+        // through the test output
+        let mut result = match result {
+            Ok(()) if !failures.is_empty() => Err(error::Error::TestsFailed(failures.join("; "))),
+            result => result,
+        };
+        if result.is_ok() {
+            if let Some(reason) = ignored_failure {
+                println!("Test command failed, but {}.", reason);
+            } else {
+                println!("Test command executed successfully.");
+            }
+        }
+
+        // Only the output of a passing run is worth keeping as a reference
+        if self.results.record_golden {
+            let capture = capture.as_ref().expect("output is captured for golden recording");
+            if result.is_err() {
+                println!("Golden output not recorded: the run failed.");
+            } else {
+                match golden::record(&self.project_root, self.feature, &capture.stdout(), &capture.stderr()) {
+                    Ok(dir) => {
+                        println!("✓ Golden output recorded in {}", dir.display());
+                        written.push(dir);
+                    }
+                    Err(e) => result = Err(error::Error::IoError(e)),
+                }
+            }
+        }
+
+        RunOutcome {
+            started_at,
+            finished_at,
+            cases,
+            result,
+            command_status,
+            status_before,
+            written,
+        }
+    }
+
+    /// Classify the parsed test results against the feature's previous run and store
+    /// them for the next one. Returns the tests that passed before and now fail
+    /// unexpectedly (`NewlyFailing`) or reported no result (`Missing`), or `None`
+    /// if this run reported no per-test results.
+    /// Like the golden output, the stored results are best-effort, so failures only warn.
+    fn track_results(&self, cases: &[results::TestCase]) -> Option<Vec<(String, regression::Change)>> {
+        if !self.track_results || cases.is_empty() {
+            return None;
+        }
+        let previous = regression::load(&self.project_root, self.feature).unwrap_or_else(|e| {
+            eprintln!("Warning: ignoring the previous test results: {}", e);
+            regression::KnownResults::new()
+        });
+        let changes = regression::classify(&previous, cases);
+        if previous.is_empty() {
+            println!("No previous test results; this run's results are the reference for the next run.");
+            println!();
+        } else {
+            regression::print_changes(&changes);
+        }
+        if let Err(e) = regression::save(&self.project_root, self.feature, &previous, cases) {
+            eprintln!(
+                "Warning: failed to store test results in {}: {}",
+                regression::results_path(&self.project_root, self.feature).display(),
+                e
+            );
+        }
+        // An expected failure is not a regression, even if the test passed before
+        let unexpected = |name: &str| {
+            cases.iter().any(|case| case.name == name && case.status == results::TestStatus::Failed)
+        };
+        Some(
+            changes
+                .into_iter()
+                .filter(|(name, change)| match change {
+                    regression::Change::NewlyFailing => unexpected(name),
+                    regression::Change::Missing => true,
+                    _ => false,
+                })
+                .collect(),
+        )
+    }
+
+    /// The auto-commit policy in effect and where it came from.
+    /// `--no-commit` and `C2RUST_TEST_NO_COMMIT` override the persisted `test.commit_policy`.
+    fn commit_policy(&self) -> (git_helper::CommitPolicy, &'static str) {
+        if self.commit.no_commit {
+            return (git_helper::CommitPolicy::Never, "--no-commit");
+        }
+        if std::env::var("C2RUST_TEST_NO_COMMIT").is_ok_and(|value| is_truthy(&value)) {
+            return (git_helper::CommitPolicy::Never, "C2RUST_TEST_NO_COMMIT");
+        }
+        match config_helper::get_config_value("test.commit_policy", Some(self.feature), &self.project_root) {
+            Ok(Some(value)) => match value.parse() {
+                Ok(policy) => return (policy, "test.commit_policy"),
+                Err(e) => eprintln!("Warning: ignoring test.commit_policy: {}", e),
+            },
+            Ok(None) => {}
+            Err(e) => eprintln!("Warning: failed to read test.commit_policy: {}", e),
+        }
+        (git_helper::CommitPolicy::OnSuccess, "default policy")
+    }
+
+    /// Lock .c2rust against concurrent invocations for the save/commit phase
+    fn lock(&self) -> Result<lock::C2rustLock> {
+        lock::C2rustLock::acquire(&self.project_root, self.commit.lock_timeout)
+    }
+
+    /// Commit the changes this run made in .c2rust, describing the run in the message.
+    /// Whether a commit is made follows `commit_policy`; a failing run counts as
+    /// a success if its configuration was recorded with `--record-on-failure`.
+    /// Auto-commit is best-effort, so failures only warn.
+    fn auto_commit(&self, subcommand: &str, outcome: &RunOutcome, recorded: bool) -> Option<String> {
+        let (policy, source) = self.commit_policy();
+        let commit = match policy {
+            git_helper::CommitPolicy::Always => true,
+            git_helper::CommitPolicy::OnSuccess => outcome.result.is_ok() || recorded,
+            git_helper::CommitPolicy::Never => false,
+        };
+        if !commit {
+            if policy == git_helper::CommitPolicy::Never {
+                println!("Auto-commit skipped ({}).", source);
+            }
+            return None;
+        }
+
+        let info = git_helper::CommitInfo {
+            subcommand,
+            feature: self.feature,
+            command: &self.test_cmd,
+            test_dir: &self.test_dir_relative,
+            passed: outcome.result.is_ok(),
+            exit_code: outcome.exit_code(),
+            duration: (outcome.finished_at - outcome.started_at).to_std().unwrap_or_default(),
+            subject: self.commit.message.as_deref(),
+            fallback_identity: &|| fallback_identity(self.feature, &self.project_root),
+            written: &outcome.written,
+        };
+        git_helper::auto_commit_if_modified(&self.project_root, &info, &outcome.status_before).unwrap_or_else(|e| {
+            eprintln!("Warning: failed to auto-commit .c2rust changes: {}", e);
+            None
+        })
+    }
+
+    /// Tag the commit of a passing run as a known-good baseline, if `--tag-baseline` was given.
+    /// Like the auto-commit itself, tagging only warns on failure.
+    fn tag_baseline(&self, outcome: &RunOutcome, commit: Option<&str>) {
+        let Some(kind) = self.commit.tag_baseline else {
+            return;
+        };
+        if outcome.result.is_err() {
+            return;
+        }
+        let Some(commit) = commit else {
+            eprintln!("Warning: not tagging a baseline: this run made no commit in .c2rust");
+            return;
+        };
+
+        let timestamp = outcome.finished_at.format("%Y%m%d-%H%M%S").to_string();
+        match git_helper::tag_baseline(
+            &self.project_root,
+            commit,
+            self.feature,
+            &timestamp,
+            kind == TagKind::Annotated,
+            &|| fallback_identity(self.feature, &self.project_root),
+        ) {
+            Ok(name) => println!("✓ Tagged baseline {}", name),
+            Err(e) => eprintln!("Warning: failed to tag baseline: {}", e),
+        }
+    }
+
+    /// Write the `--report` file, if one was requested.
+    /// The report is a by-product of the run, so failing to write it only warns.
+    fn write_report(&self, outcome: &RunOutcome, config_saved: bool, commit: Option<&str>) {
+        let Some(path) = &self.results.report else {
+            return;
+        };
+        let path = report::resolve_path(path, &self.invoked_from);
+
+        let mut run_report = report::RunReport::new(
+            &self.project_root,
+            &self.test_dir_relative,
+            self.feature,
+            &self.test_cmd,
+            outcome.started_at,
+            outcome.finished_at,
+            outcome.command_status,
+            &outcome.result,
+            &outcome.cases,
+        );
+        run_report.config_saved = config_saved;
+        run_report.commit = commit;
+
+        match run_report.write(&path) {
+            Ok(()) => println!("Report written to {}", path.display()),
+            Err(e) => eprintln!("Warning: failed to write report {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// What happened when a `TestRun` was executed
+struct RunOutcome {
+    started_at: chrono::DateTime<chrono::Local>,
+    finished_at: chrono::DateTime<chrono::Local>,
+    /// Per-test results from every enabled result parser
+    cases: Vec<results::TestCase>,
+    /// `Ok` if the command and its parsed results passed
+    result: Result<()>,
+    /// How the test command ended, which `result` may override
+    command_status: executor::CommandStatus,
+    /// Uncommitted .c2rust changes from before the run, kept out of the auto-commit
+    status_before: git_helper::StatusSnapshot,
+    /// Run output this invocation wrote in .c2rust: its log, golden output and results
+    written: Vec<PathBuf>,
+}
+
+impl RunOutcome {
+    /// Exit code c2rust-test reports for this run
+    fn exit_code(&self) -> i32 {
+        self.result.as_ref().err().map_or(0, |e| e.exit_code())
+    }
+}
+
 fn run(args: CommandArgs) -> Result<()> {
     // 1. Check if c2rust-config exists
     config_helper::check_c2rust_config_exists()?;
@@ -42,7 +771,7 @@ fn run(args: CommandArgs) -> Result<()> {
 
     // 3. Get the current working directory (where the command is executed)
     let current_dir = std::env::current_dir()
-        .map_err(|e| error::Error::IoError(e))?;
+        .map_err(error::Error::IoError)?;
     
     // 4. Find the project root (where .c2rust will be created)
     // Start from current directory and search upward for .c2rust or use current as root
@@ -64,32 +793,373 @@ fn run(args: CommandArgs) -> Result<()> {
             ".".to_string()
         });
 
-    println!("=== c2rust-test ===");
-    println!("Project root: {}", project_root.display());
-    println!("Test directory (relative): {}", test_dir_relative);
-    println!("Feature: {}", feature);
-    println!("Command: {}", args.test_cmd.join(" "));
-    println!();
+    let test_run = TestRun {
+        invoked_from: current_dir.clone(),
+        project_root,
+        test_dir: current_dir,
+        test_dir_relative,
+        feature,
+        test_cmd: args.test_cmd,
+        timeout: args.timeout,
+        env: args.env,
+        shell: args.shell.then(executor::default_shell),
+        results: args.results,
+        commit: args.commit,
+        capture: None,
+        track_results: true,
+    };
+    test_run.print_header();
+
+    if args.dry_run {
+        return test_run.dry_run();
+    }
     
     // 6. Execute the test command in the current directory
-    executor::execute_command(&current_dir, &args.test_cmd)?;
-
-    println!("Test command executed successfully.");
+    let outcome = test_run.execute();
+    let passed = outcome.result.is_ok();
+    let record = passed || args.record_on_failure;
     
-    // 7. Save configuration using c2rust-config
-    let command_str = args.test_cmd.join(" ");
-    config_helper::save_config(&test_dir_relative, &command_str, Some(feature), &project_root)?;
-    println!("✓ Configuration saved.");
+    // Saving and committing must not interleave with another invocation's
+    let _lock = match test_run.lock() {
+        Ok(lock) => lock,
+        Err(e) => {
+            test_run.write_report(&outcome, false, None);
+            return outcome.result.and(Err(e));
+        }
+    };
+
+    // 7. Save configuration using c2rust-config, together with the run's result
+    if record {
+        let mut test_config = test_run.config();
+        test_config.last_run = Some(config_helper::LastRun {
+            passed,
+            exit_code: outcome.exit_code(),
+        });
+        if let Err(e) = config_helper::save_config(&test_config, Some(feature), &test_run.project_root) {
+            test_run.write_report(&outcome, false, None);
+            // The test failure is the more important error to report
+            return outcome.result.and(Err(e));
+        }
+        if passed {
+            println!("✓ Configuration saved.");
+        } else {
+            println!("✓ Configuration saved (test.last_status=fail).");
+        }
+    }
 
     // Auto-commit changes in .c2rust directory if any
     // This is a best-effort operation: failures should not cause the CLI to exit with an error
-    if let Err(e) = git_helper::auto_commit_if_modified(&project_root) {
-        eprintln!("Warning: failed to auto-commit .c2rust changes: {}", e);
+    let commit = test_run.auto_commit("test", &outcome, record);
+    test_run.tag_baseline(&outcome, commit.as_deref());
+
+    test_run.write_report(&outcome, record, commit.as_deref());
+    outcome.result
+}
+
+/// Re-run the test command recorded in the c2rust config
+fn replay(args: RunArgs) -> Result<()> {
+    config_helper::check_c2rust_config_exists()?;
+
+    let feature = args.feature.as_deref().unwrap_or("default");
+
+    let current_dir = std::env::current_dir()
+        .map_err(error::Error::IoError)?;
+    let project_root = find_project_root(&current_dir)?;
+
+    // Read back the saved test command; test.dir is relative to the project root
+    let test_config = config_helper::load_config(Some(feature), &project_root)?;
+
+    let test_run = TestRun::from_config(current_dir, project_root, feature, test_config, args.results, args.commit);
+    test_run.print_header();
+    let outcome = test_run.execute();
+    let _lock = match test_run.lock() {
+        Ok(lock) => lock,
+        Err(e) => {
+            test_run.write_report(&outcome, false, None);
+            return outcome.result.and(Err(e));
+        }
+    };
+
+    // The configuration is unchanged, but the run log still needs committing
+    let commit = test_run.auto_commit("run", &outcome, false);
+    test_run.tag_baseline(&outcome, commit.as_deref());
+
+    test_run.write_report(&outcome, false, commit.as_deref());
+    outcome.result
+}
+
+/// Run the saved test command once against the C build and once against the
+/// Rust build, as selected by test.c.* and test.rust.*, and compare the runs
+fn diff(args: DiffArgs) -> Result<()> {
+    config_helper::check_c2rust_config_exists()?;
+
+    let feature = args.feature.as_deref().unwrap_or("default");
+
+    let current_dir = std::env::current_dir()
+        .map_err(error::Error::IoError)?;
+    let project_root = find_project_root(&current_dir)?;
+
+    let test_config = config_helper::load_config(Some(feature), &project_root)?;
+    let c_overrides = config_helper::load_side_overrides("c", Some(feature), &project_root)?;
+    let rust_overrides = config_helper::load_side_overrides("rust", Some(feature), &project_root)?;
+    if c_overrides.is_empty() && rust_overrides.is_empty() {
+        return Err(error::Error::ConfigLoadFailed(
+            "no C/Rust builds configured; set test.c.dir/test.c.env and test.rust.dir/test.rust.env".to_string(),
+        ));
+    }
+    let normalizer = normalize::load(Some(feature), &project_root)?;
+
+    let c_run = TestRun::from_config(
+        current_dir.clone(),
+        project_root.clone(),
+        feature,
+        test_config.with_overrides(&c_overrides),
+        ResultArgs::default(),
+        CommitArgs::default(),
+    );
+    // The Rust side carries the commit options, since its run completes the diff
+    let rust_run = TestRun::from_config(
+        current_dir,
+        project_root,
+        feature,
+        test_config.with_overrides(&rust_overrides),
+        ResultArgs::default(),
+        args.commit,
+    );
+
+    let mut sides = Vec::new();
+    for (label, mut test_run) in [("C", c_run), ("Rust", rust_run)] {
+        let capture = executor::CapturedOutput::default();
+        test_run.capture = Some(capture.clone());
+        // The two sides are not successive runs of the same build
+        test_run.track_results = false;
+        println!("=== {} build ===", label);
+        test_run.print_header();
+        let outcome = test_run.execute();
+        let output = compare::SideOutput {
+            exit_code: outcome.exit_code(),
+            stdout: normalizer.apply(&capture.stdout()),
+            stderr: normalizer.apply(&capture.stderr()),
+        };
+        sides.push((test_run, outcome, output));
+    }
+    let (rust_run, rust_outcome, rust_output) = sides.pop().expect("two sides were run");
+    let (_, c_outcome, c_output) = sides.pop().expect("two sides were run");
+
+    println!("=== C vs Rust ===");
+    let divergences = compare::compare(&c_output, &rust_output);
+    let result = if divergences.is_empty() {
+        println!("✓ C and Rust builds behave identically (exit code, stdout, stderr).");
+        Ok(())
+    } else {
+        for divergence in &divergences {
+            divergence.print(compare::C_VS_RUST);
+        }
+        let summaries: Vec<String> = divergences.iter().map(|divergence| divergence.summary(compare::C_VS_RUST)).collect();
+        Err(error::Error::TestsFailed(format!("C and Rust builds diverge: {}", summaries.join("; "))))
+    };
+    println!();
+
+    // Both run logs are committed together, described as one diff run
+    let outcome = RunOutcome {
+        started_at: c_outcome.started_at,
+        finished_at: rust_outcome.finished_at,
+        cases: Vec::new(),
+        result,
+        command_status: rust_outcome.command_status,
+        status_before: c_outcome.status_before,
+        written: [c_outcome.written, rust_outcome.written].concat(),
+    };
+    let _lock = match rust_run.lock() {
+        Ok(lock) => lock,
+        Err(e) => return outcome.result.and(Err(e)),
+    };
+    let commit = rust_run.auto_commit("diff", &outcome, false);
+    rust_run.tag_baseline(&outcome, commit.as_deref());
+    outcome.result
+}
+
+/// Print the test runs recorded in the .c2rust repository
+fn history(args: HistoryArgs) -> Result<()> {
+    let current_dir = std::env::current_dir()
+        .map_err(error::Error::IoError)?;
+    let project_root = find_project_root(&current_dir)?;
+
+    let entries = git_helper::history(&project_root, args.feature.as_deref(), args.limit)
+        .map_err(error::Error::GitFailed)?;
+    if entries.is_empty() {
+        println!("No test runs recorded.");
+        return Ok(());
+    }
+    for entry in entries {
+        let mut details = Vec::new();
+        if let Some(duration) = &entry.duration {
+            details.push(duration.clone());
+        }
+        if let Some(exit_code) = entry.exit_code {
+            details.push(format!("exit {}", exit_code));
+        }
+        println!(
+            "{}  {}  {:<4}  [{}]  {} {}  ({})",
+            entry.time.format("%Y-%m-%d %H:%M:%S"),
+            &entry.commit[..entry.commit.len().min(10)],
+            entry.status.to_uppercase(),
+            entry.feature,
+            entry.subcommand,
+            entry.command,
+            details.join(", "),
+        );
     }
+    Ok(())
+}
 
+/// Print the baseline tags of the .c2rust repository
+fn list_baselines(feature: Option<&str>) -> Result<()> {
+    let current_dir = std::env::current_dir()
+        .map_err(error::Error::IoError)?;
+    let project_root = find_project_root(&current_dir)?;
+
+    let baselines = git_helper::list_baselines(&project_root, feature).map_err(error::Error::GitFailed)?;
+    if baselines.is_empty() {
+        println!("No baselines found.");
+        return Ok(());
+    }
+    for baseline in baselines {
+        println!(
+            "{}  {}  {}  {}",
+            baseline.name,
+            &baseline.commit[..baseline.commit.len().min(10)],
+            baseline.time.format("%Y-%m-%d %H:%M:%S"),
+            baseline.summary,
+        );
+    }
     Ok(())
 }
 
+/// Add tests to, or remove them from, a feature's xfail list and commit the change
+fn edit_xfail(args: XfailArgs, add: bool) -> Result<()> {
+    let feature = args.feature.as_deref().unwrap_or("default");
+    let current_dir = std::env::current_dir()
+        .map_err(error::Error::IoError)?;
+    let project_root = find_project_root(&current_dir)?;
+
+    let _lock = lock::C2rustLock::acquire(&project_root, None)?;
+    let before = git_helper::StatusSnapshot::take(&project_root).unwrap_or_else(|e| {
+        eprintln!("Warning: failed to inspect .c2rust, all its changes will be committed: {}", e);
+        git_helper::StatusSnapshot::default()
+    });
+
+    let mut list = xfail::load(&project_root, feature)?;
+    let mut changed = Vec::new();
+    for test in args.tests {
+        let updated = if add { list.insert(test.clone()) } else { list.remove(&test) };
+        if updated {
+            changed.push(test);
+        } else if add {
+            println!("{} is already in the xfail list of {}.", test, feature);
+        } else {
+            println!("{} is not in the xfail list of {}.", test, feature);
+        }
+    }
+    if changed.is_empty() {
+        return Ok(());
+    }
+    xfail::save(&project_root, feature, &list)?;
+
+    let action = if add { "add" } else { "remove" };
+    for test in &changed {
+        println!("✓ {} {}", if add { "Added" } else { "Removed" }, test);
+    }
+    let summary = format!("xfail {} {}", action, shell_words::join(&changed));
+    match git_helper::commit_edit(
+        &project_root,
+        "xfail",
+        feature,
+        &summary,
+        &|| fallback_identity(feature, &project_root),
+        &before,
+        &[],
+    ) {
+        Ok(Some(commit)) => println!("✓ Committed {}", &commit[..commit.len().min(10)]),
+        Ok(None) => {}
+        Err(e) => eprintln!("Warning: failed to commit the xfail list: {}", e),
+    }
+    Ok(())
+}
+
+/// Print the expected failures of a feature
+fn list_xfail(feature: Option<&str>) -> Result<()> {
+    let feature = feature.unwrap_or("default");
+    let current_dir = std::env::current_dir()
+        .map_err(error::Error::IoError)?;
+    let project_root = find_project_root(&current_dir)?;
+
+    let list = xfail::load(&project_root, feature)?;
+    if list.is_empty() {
+        println!("No expected failures for {}.", feature);
+        return Ok(());
+    }
+    for test in list {
+        println!("{}", test);
+    }
+    Ok(())
+}
+
+/// Commit author for when git has no user identity configured. Each field comes from
+/// C2RUST_TEST_AUTHOR_NAME/_EMAIL, else test.author_name/test.author_email, else the default.
+fn fallback_identity(feature: &str, project_root: &Path) -> git_helper::FallbackIdentity {
+    let default = git_helper::FallbackIdentity::default();
+    let value = |env_var: &str, key: &str, default: String| {
+        if let Some(value) = std::env::var(env_var).ok().filter(|value| !value.is_empty()) {
+            return value;
+        }
+        match config_helper::get_config_value(key, Some(feature), project_root) {
+            Ok(value) => value.unwrap_or(default),
+            Err(e) => {
+                eprintln!("Warning: failed to read {}: {}", key, e);
+                default
+            }
+        }
+    };
+    git_helper::FallbackIdentity {
+        name: value("C2RUST_TEST_AUTHOR_NAME", "test.author_name", default.name),
+        email: value("C2RUST_TEST_AUTHOR_EMAIL", "test.author_email", default.email),
+    }
+}
+
+/// Parse a `--feature` name. It names a directory in .c2rust, so it must be a
+/// single path component: logs or results must not end up outside .c2rust.
+fn parse_feature(value: &str) -> std::result::Result<String, String> {
+    if value.is_empty() || value == "." || value == ".." || value.contains(['/', '\\']) {
+        return Err(format!("invalid feature name '{}': it must be a single directory name", value));
+    }
+    Ok(value.to_string())
+}
+
+/// Parse a `KEY=VALUE` environment assignment given to `--env`
+fn parse_env_assignment(value: &str) -> std::result::Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", value)),
+    }
+}
+
+/// Whether an environment flag such as `C2RUST_TEST_NO_COMMIT` is switched on
+fn is_truthy(value: &str) -> bool {
+    !matches!(value.trim().to_ascii_lowercase().as_str(), "" | "0" | "false" | "no" | "off")
+}
+
+/// Path of the log file for a new run of `feature`:
+/// `<project_root>/.c2rust/<feature>/test-logs/<timestamp>.log`
+fn run_log_path(project_root: &Path, feature: &str) -> PathBuf {
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+    project_root
+        .join(".c2rust")
+        .join(feature)
+        .join("test-logs")
+        .join(format!("{}.log", timestamp))
+}
+
 /// Find the project root directory.
 /// Searches upward from start_dir for Cargo.toml, .git, or .c2rust directory.
 /// If not found, returns the start_dir as the project root.
@@ -161,9 +1231,21 @@ fn find_project_root(start_dir: &Path) -> Result<PathBuf> {
 
 fn main() {
     let cli = Cli::parse();
+    if let Commands::Test(args) = &cli.command {
+        if let Err(e) = args.shell.then(|| executor::shell_script(&args.test_cmd)).transpose() {
+            Cli::command().error(clap::error::ErrorKind::ArgumentConflict, format!("--shell: {}", e)).exit();
+        }
+    }
 
     let result = match cli.command {
         Commands::Test(args) => run(args),
+        Commands::Run(args) => replay(args),
+        Commands::Diff(args) => diff(args),
+        Commands::History(args) => history(args),
+        Commands::Baseline { command: BaselineCommand::List { feature } } => list_baselines(feature.as_deref()),
+        Commands::Xfail { command: XfailCommand::Add(args) } => edit_xfail(args, true),
+        Commands::Xfail { command: XfailCommand::Remove(args) } => edit_xfail(args, false),
+        Commands::Xfail { command: XfailCommand::List { feature } } => list_xfail(feature.as_deref()),
     };
 
     if let Err(e) = result {
@@ -0,0 +1,112 @@
+use crate::config_helper;
+use crate::error::{Error, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Built-in rules that can be named in `test.normalize` instead of writing the regex
+const PRESETS: &[(&str, &str, &str)] = &[
+    ("hex-addresses", r"0[xX][0-9a-fA-F]+", "0xADDR"),
+    (
+        "iso-dates",
+        r"\d{4}-\d{2}-\d{2}(?:[T ]\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z|[+-]\d{2}:?\d{2})?)?",
+        "<DATE>",
+    ),
+    ("tmp-paths", r#"/tmp/[^\s'"`:,;)\]]*"#, "/tmp/<PATH>"),
+];
+
+/// One entry of `test.normalize`: a preset name or a custom substitution
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RuleSpec {
+    Preset(String),
+    Custom { pattern: String, replace: String },
+}
+
+/// Regex substitutions applied to captured output before it is compared,
+/// so that addresses, timestamps and temporary paths do not count as differences
+#[derive(Debug, Default)]
+pub struct Normalizer {
+    rules: Vec<(Regex, String)>,
+}
+
+impl Normalizer {
+    /// Parse the JSON array stored in `test.normalize`, e.g.
+    /// `["hex-addresses", {"pattern": "pid \\d+", "replace": "pid N"}]`
+    pub fn parse(value: &str) -> std::result::Result<Self, String> {
+        let specs: Vec<RuleSpec> = serde_json::from_str(value).map_err(|e| {
+            format!("expected a JSON array of preset names or {{\"pattern\", \"replace\"}} objects: {}", e)
+        })?;
+
+        let mut rules = Vec::new();
+        for spec in specs {
+            let (pattern, replace) = match spec {
+                RuleSpec::Preset(name) => {
+                    let (_, pattern, replace) = PRESETS
+                        .iter()
+                        .find(|(preset, _, _)| *preset == name)
+                        .ok_or_else(|| format!("unknown preset '{}' (available: {})", name, preset_names()))?;
+                    (pattern.to_string(), replace.to_string())
+                }
+                RuleSpec::Custom { pattern, replace } => (pattern, replace),
+            };
+            let regex = Regex::new(&pattern).map_err(|e| format!("invalid pattern '{}': {}", pattern, e))?;
+            rules.push((regex, replace));
+        }
+        Ok(Normalizer { rules })
+    }
+
+    /// Apply every rule in order
+    pub fn apply(&self, text: &str) -> String {
+        self.rules
+            .iter()
+            .fold(text.to_string(), |text, (regex, replace)| regex.replace_all(&text, replace.as_str()).into_owned())
+    }
+}
+
+fn preset_names() -> String {
+    PRESETS.iter().map(|(name, _, _)| *name).collect::<Vec<_>>().join(", ")
+}
+
+/// Load the feature's normalization rules from `test.normalize`; none if it is unset
+pub fn load(feature: Option<&str>, project_root: &Path) -> Result<Normalizer> {
+    match config_helper::get_config_value("test.normalize", feature, project_root)? {
+        Some(value) => Normalizer::parse(&value).map_err(|e| Error::ConfigLoadFailed(format!("test.normalize: {}", e))),
+        None => Ok(Normalizer::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        let normalizer = Normalizer::parse(r#"["hex-addresses", "iso-dates", "tmp-paths"]"#).unwrap();
+        let output = "node at 0x7ffd5e8c1a20 freed\n\
+                      built 2026-10-18T12:00:01.5+02:00 on 2026-10-18\n\
+                      wrote /tmp/tmp.Xa81/out.txt: ok\n";
+        assert_eq!(
+            normalizer.apply(output),
+            "node at 0xADDR freed\nbuilt <DATE> on <DATE>\nwrote /tmp/<PATH>: ok\n"
+        );
+    }
+
+    #[test]
+    fn test_custom_rules_apply_in_order() {
+        let normalizer = Normalizer::parse(
+            r#"[{"pattern": "pid \\d+", "replace": "pid N"}, {"pattern": "(\\w+)=N", "replace": "$1=?"}]"#,
+        )
+        .unwrap();
+        assert_eq!(normalizer.apply("child pid 4242 exited"), "child pid N exited");
+        assert_eq!(normalizer.apply("x=N"), "x=?");
+        assert_eq!(Normalizer::parse("[]").unwrap().apply("x=N"), "x=N");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Normalizer::parse(r#"["no-such-preset"]"#).unwrap_err().contains("unknown preset"));
+        assert!(Normalizer::parse(r#"[{"pattern": "(", "replace": ""}]"#).unwrap_err().contains("invalid pattern"));
+        assert!(Normalizer::parse("hex-addresses").is_err());
+    }
+}
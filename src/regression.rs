@@ -0,0 +1,198 @@
+use crate::results::{TestCase, TestStatus};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File holding the last known status of each test, inside the feature's directory
+const RESULTS_FILE: &str = "results.json";
+
+/// Last known status of each test of a feature, by test name
+pub type KnownResults = BTreeMap<String, TestStatus>;
+
+/// How a test's status changed since the previous run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// Passed in the previous run, fails now: a regression
+    NewlyFailing,
+    NewlyPassing,
+    StillFailing,
+    StillPassing,
+    /// No earlier result is known
+    New,
+    /// Passed in the previous run but reported no result in this one, as when
+    /// the test program crashed before reaching it: also a regression
+    Missing,
+}
+
+impl Change {
+    /// Fixed-width label used when listing changes
+    pub fn label(self) -> &'static str {
+        match self {
+            Change::NewlyFailing => "NEWLY FAILING",
+            Change::NewlyPassing => "NEWLY PASSING",
+            Change::StillFailing => "STILL FAILING",
+            Change::StillPassing => "STILL PASSING",
+            Change::New => "NEW",
+            Change::Missing => "MISSING",
+        }
+    }
+}
+
+/// Path of a feature's stored results: `<project_root>/.c2rust/<feature>/results.json`
+pub fn results_path(project_root: &Path, feature: &str) -> PathBuf {
+    project_root.join(".c2rust").join(feature).join(RESULTS_FILE)
+}
+
+/// Read the stored results of a feature; empty if none were stored yet
+pub fn load(project_root: &Path, feature: &str) -> std::result::Result<KnownResults, String> {
+    let path = results_path(project_root, feature);
+    match std::fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| format!("invalid {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(KnownResults::new()),
+        Err(e) => Err(format!("failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Store the results of this run on top of the previous ones. Skipped tests and
+/// tests that did not run keep their last known status.
+pub fn save(project_root: &Path, feature: &str, previous: &KnownResults, cases: &[TestCase]) -> std::io::Result<()> {
+    let mut known = previous.clone();
+    for case in cases {
+        if let Some(status) = outcome(case.status) {
+            known.insert(case.name.clone(), status);
+        }
+    }
+    let path = results_path(project_root, feature);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(&known).map_err(std::io::Error::other)?;
+    std::fs::write(path, json + "\n")
+}
+
+/// Whether a test actually passed or failed, regardless of the xfail list;
+/// `None` for a skipped test
+fn outcome(status: TestStatus) -> Option<TestStatus> {
+    match status {
+        TestStatus::Passed | TestStatus::UnexpectedPass => Some(TestStatus::Passed),
+        TestStatus::Failed | TestStatus::ExpectedFailure => Some(TestStatus::Failed),
+        TestStatus::Skipped => None,
+    }
+}
+
+/// Classify every passing or failing test of this run against the previous results,
+/// in the order the tests were reported, followed by the previously passing tests
+/// this run did not report at all. Skipped tests are not classified.
+pub fn classify(previous: &KnownResults, cases: &[TestCase]) -> Vec<(String, Change)> {
+    let mut changes: Vec<(String, Change)> = Vec::new();
+    for case in cases {
+        let Some(status) = outcome(case.status) else {
+            continue;
+        };
+        let passed = status == TestStatus::Passed;
+        let change = match previous.get(&case.name) {
+            Some(TestStatus::Passed) if passed => Change::StillPassing,
+            Some(TestStatus::Passed) => Change::NewlyFailing,
+            Some(TestStatus::Failed) if passed => Change::NewlyPassing,
+            Some(TestStatus::Failed) => Change::StillFailing,
+            _ => Change::New,
+        };
+        // A test reported twice (e.g. by TAP and JUnit) is classified by its last result
+        match changes.iter_mut().find(|(name, _)| *name == case.name) {
+            Some(entry) => entry.1 = change,
+            None => changes.push((case.name.clone(), change)),
+        }
+    }
+    for (name, status) in previous {
+        if *status == TestStatus::Passed && !cases.iter().any(|case| case.name == *name) {
+            changes.push((name.clone(), Change::Missing));
+        }
+    }
+    changes
+}
+
+/// Print the tests whose status changed, followed by the count of each kind of change
+pub fn print_changes(changes: &[(String, Change)]) {
+    println!("Compared with the previous run:");
+    for (name, change) in changes {
+        if !matches!(change, Change::StillFailing | Change::StillPassing) {
+            println!("  {:<13}  {}", change.label(), name);
+        }
+    }
+    let count = |kind: Change| changes.iter().filter(|(_, change)| *change == kind).count();
+    let missing = count(Change::Missing);
+    println!(
+        "{} tests: {} newly failing, {} newly passing, {} still failing, {} still passing, {} new{}",
+        changes.len() - missing,
+        count(Change::NewlyFailing),
+        count(Change::NewlyPassing),
+        count(Change::StillFailing),
+        count(Change::StillPassing),
+        count(Change::New),
+        if missing > 0 { format!(", {} missing", missing) } else { String::new() },
+    );
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn case(name: &str, status: TestStatus) -> TestCase {
+        TestCase {
+            name: name.to_string(),
+            status,
+            duration: None,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        let previous: KnownResults = [
+            ("a".to_string(), TestStatus::Passed),
+            ("b".to_string(), TestStatus::Passed),
+            ("c".to_string(), TestStatus::Failed),
+            ("d".to_string(), TestStatus::Failed),
+            ("f".to_string(), TestStatus::Passed),
+            ("g".to_string(), TestStatus::Passed),
+            ("h".to_string(), TestStatus::Failed),
+        ]
+        .into();
+        let cases = [
+            case("a", TestStatus::Passed),
+            case("b", TestStatus::Failed),
+            case("c", TestStatus::Passed),
+            case("d", TestStatus::Failed),
+            case("e", TestStatus::ExpectedFailure),
+            case("f", TestStatus::Skipped),
+        ];
+        assert_eq!(
+            classify(&previous, &cases),
+            vec![
+                ("a".to_string(), Change::StillPassing),
+                ("b".to_string(), Change::NewlyFailing),
+                ("c".to_string(), Change::NewlyPassing),
+                ("d".to_string(), Change::StillFailing),
+                ("e".to_string(), Change::New),
+                ("g".to_string(), Change::Missing),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_save_keeps_tests_that_did_not_run() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(load(temp_dir.path(), "f1").unwrap().is_empty());
+
+        save(temp_dir.path(), "f1", &KnownResults::new(), &[case("a", TestStatus::Passed), case("b", TestStatus::Failed)])
+            .unwrap();
+        let previous = load(temp_dir.path(), "f1").unwrap();
+        save(temp_dir.path(), "f1", &previous, &[case("a", TestStatus::Skipped), case("b", TestStatus::Passed)]).unwrap();
+
+        let known = load(temp_dir.path(), "f1").unwrap();
+        assert_eq!(known.get("a"), Some(&TestStatus::Passed));
+        assert_eq!(known.get("b"), Some(&TestStatus::Passed));
+        assert!(results_path(temp_dir.path(), "f1").ends_with(".c2rust/f1/results.json"));
+    }
+}
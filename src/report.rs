@@ -0,0 +1,123 @@
+use crate::error::{Error, Result};
+use crate::executor::CommandStatus;
+use crate::results::TestCase;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Machine-readable description of one test run, written by `--report`
+#[derive(Serialize)]
+pub struct RunReport<'a> {
+    pub project_root: &'a Path,
+    /// Test directory relative to the project root
+    pub test_dir: &'a str,
+    pub feature: &'a str,
+    pub argv: &'a [String],
+    pub started_at: String,
+    pub finished_at: String,
+    pub duration_secs: f64,
+    /// Exit code of the test command; `None` if it was killed or never started
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the test command
+    pub signal: Option<i32>,
+    pub timed_out: bool,
+    /// Whether the run passed, including any failures found in parsed results
+    pub success: bool,
+    pub error: Option<String>,
+    pub config_saved: bool,
+    /// Commit created in the .c2rust repository for this run
+    pub commit: Option<&'a str>,
+    pub tests: &'a [TestCase],
+}
+
+impl<'a> RunReport<'a> {
+    /// Fill in the timing and status fields from the outcome of the run. The exit
+    /// fields describe the test command itself, even where `result` overrides it
+    /// (e.g. a failing exit status ignored because of the xfail list).
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        project_root: &'a Path,
+        test_dir: &'a str,
+        feature: &'a str,
+        argv: &'a [String],
+        started_at: DateTime<Local>,
+        finished_at: DateTime<Local>,
+        status: CommandStatus,
+        result: &Result<()>,
+        tests: &'a [TestCase],
+    ) -> Self {
+        RunReport {
+            project_root,
+            test_dir,
+            feature,
+            argv,
+            started_at: started_at.to_rfc3339(),
+            finished_at: finished_at.to_rfc3339(),
+            duration_secs: (finished_at - started_at).to_std().unwrap_or_default().as_secs_f64(),
+            exit_code: status.exit_code,
+            signal: status.signal,
+            timed_out: status.timed_out,
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            config_saved: false,
+            commit: None,
+            tests,
+        }
+    }
+
+    /// Write the report as pretty-printed JSON, creating parent directories as needed
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::IoError(std::io::Error::other(e)))?;
+        std::fs::write(path, json + "\n")?;
+        Ok(())
+    }
+}
+
+/// Resolve a `--report` path against the directory c2rust-test was started in
+pub fn resolve_path(path: &Path, current_dir: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        current_dir.join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::TestStatus;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_report_json() {
+        let temp_dir = TempDir::new().unwrap();
+        let argv = vec!["make".to_string(), "test".to_string()];
+        let tests = vec![TestCase {
+            name: "a".to_string(),
+            status: TestStatus::Failed,
+            duration: Some(std::time::Duration::from_millis(500)),
+            message: None,
+        }];
+        let started = Local::now();
+        let result = Err(Error::CommandTerminatedBySignal("segfault".to_string(), 11));
+        let status = CommandStatus::of(&result);
+
+        let mut report = RunReport::new(temp_dir.path(), ".", "default", &argv, started, started, status, &result, &tests);
+        report.commit = Some("abc123");
+        let path = temp_dir.path().join("out/report.json");
+        report.write(&path).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["argv"][1], "test");
+        assert_eq!(json["exit_code"], serde_json::Value::Null);
+        assert_eq!(json["signal"], 11);
+        assert_eq!(json["success"], false);
+        assert_eq!(json["commit"], "abc123");
+        assert_eq!(json["tests"][0]["status"], "failed");
+        assert_eq!(json["tests"][0]["duration_secs"], 0.5);
+    }
+}
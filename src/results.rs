@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+/// File modification times come from the kernel's coarse clock and can lag the
+/// wall clock the run's start time was read from, so result files written by a
+/// fast command may appear to predate it by this much
+const MTIME_SLACK: Duration = Duration::from_secs(2);
+
+/// Outcome of a single test case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+    /// Failed, but listed in the feature's xfail list; counts as OK
+    #[serde(rename = "xfail")]
+    ExpectedFailure,
+    /// Passed although listed in the feature's xfail list
+    #[serde(rename = "xpass")]
+    UnexpectedPass,
+}
+
+impl TestStatus {
+    /// Short fixed-width label used in summaries
+    pub fn label(self) -> &'static str {
+        match self {
+            TestStatus::Passed => "PASS",
+            TestStatus::Failed => "FAIL",
+            TestStatus::Skipped => "SKIP",
+            TestStatus::ExpectedFailure => "XFAIL",
+            TestStatus::UnexpectedPass => "XPASS",
+        }
+    }
+}
+
+/// A single test case parsed from the output of the test command
+#[derive(Debug, Clone, Serialize)]
+pub struct TestCase {
+    pub name: String,
+    pub status: TestStatus,
+    #[serde(rename = "duration_secs", serialize_with = "serialize_secs")]
+    pub duration: Option<Duration>,
+    /// Failure or skip reason, if the harness reported one
+    pub message: Option<String>,
+}
+
+fn serialize_secs<S: serde::Serializer>(duration: &Option<Duration>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    duration.map(|d| d.as_secs_f64()).serialize(serializer)
+}
+
+/// Whether a result file modified at `modified` was written by a run that started at `since`
+pub fn written_since(modified: SystemTime, since: SystemTime) -> bool {
+    modified >= since.checked_sub(MTIME_SLACK).unwrap_or(since)
+}
+
+/// Number of test cases with the given status
+pub fn count(cases: &[TestCase], status: TestStatus) -> usize {
+    cases.iter().filter(|case| case.status == status).count()
+}
+
+/// Print one line per test case followed by the totals
+pub fn print_summary(source: &str, cases: &[TestCase]) {
+    println!("Test results ({}):", source);
+    for case in cases {
+        print_case(case);
+    }
+    print_totals(cases);
+    println!();
+}
+
+/// Print only the tests that did not pass, followed by the totals.
+/// Used for harnesses that already list every test in their own output.
+pub fn print_compact_summary(source: &str, cases: &[TestCase]) {
+    println!("Test results ({}):", source);
+    for case in cases.iter().filter(|case| case.status != TestStatus::Passed) {
+        print_case(case);
+    }
+    print_totals(cases);
+    let total: Duration = cases.iter().filter_map(|case| case.duration).sum();
+    if !total.is_zero() {
+        println!("Total test time: {:.2}s", total.as_secs_f64());
+    }
+    println!();
+}
+
+fn print_case(case: &TestCase) {
+    let mut line = format!("  {}  {}", case.status.label(), case.name);
+    if let Some(duration) = case.duration {
+        line.push_str(&format!(" ({:.2}s)", duration.as_secs_f64()));
+    }
+    if let Some(message) = case.message.as_deref().filter(|m| !m.is_empty()) {
+        line.push_str(&format!(": {}", message));
+    }
+    println!("{}", line);
+}
+
+fn print_totals(cases: &[TestCase]) {
+    let mut totals = format!(
+        "{} tests: {} passed, {} failed, {} skipped",
+        cases.len(),
+        count(cases, TestStatus::Passed),
+        count(cases, TestStatus::Failed),
+        count(cases, TestStatus::Skipped),
+    );
+    let xfail = count(cases, TestStatus::ExpectedFailure);
+    let xpass = count(cases, TestStatus::UnexpectedPass);
+    if xfail + xpass > 0 {
+        totals.push_str(&format!(", {} xfail, {} xpass", xfail, xpass));
+    }
+    println!("{}", totals);
+}
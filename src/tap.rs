@@ -0,0 +1,246 @@
+use crate::results::{self, TestCase, TestStatus};
+use crate::xfail::{self, XfailList};
+
+/// Incremental parser for a TAP (Test Anything Protocol) stream.
+///
+/// Lines are fed one at a time while the test command is still running.
+/// Only top-level test lines are interpreted; indented subtest output,
+/// diagnostics and unknown lines are ignored.
+#[derive(Debug, Default)]
+pub struct TapParser {
+    plan: Option<usize>,
+    cases: Vec<TestCase>,
+    todo: usize,
+    bail_out: Option<String>,
+    /// Tests whose failure is expected and does not fail the stream
+    expected_failures: XfailList,
+}
+
+/// Result of parsing a complete TAP stream
+#[derive(Debug)]
+pub struct TapSummary {
+    pub cases: Vec<TestCase>,
+    pub ok: usize,
+    pub not_ok: usize,
+    pub skip: usize,
+    pub todo: usize,
+    pub xfail: usize,
+    pub xpass: usize,
+    pub plan: Option<usize>,
+    /// Reasons the stream counts as a failure; empty when it passed
+    pub problems: Vec<String>,
+    /// The problems that are not about individual tests: a bail out or a
+    /// missing or mismatched plan means some tests may not have run
+    pub incomplete: Vec<String>,
+}
+
+impl TapSummary {
+    pub fn is_success(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Print the per-test summary followed by the TAP counts and any problems
+    pub fn print(&self) {
+        results::print_summary("TAP", &self.cases);
+        let expected = if self.xfail + self.xpass > 0 {
+            format!(", {} xfail, {} xpass", self.xfail, self.xpass)
+        } else {
+            String::new()
+        };
+        println!(
+            "TAP: {} ok, {} not ok, {} skip, {} todo{}{}",
+            self.ok,
+            self.not_ok,
+            self.skip,
+            self.todo,
+            expected,
+            self.plan.map(|n| format!(" (plan 1..{})", n)).unwrap_or_default(),
+        );
+        for problem in &self.problems {
+            println!("TAP failure: {}", problem);
+        }
+        println!();
+    }
+}
+
+impl TapParser {
+    /// A parser that counts the failures of the listed tests as expected
+    pub fn expecting_failures(expected_failures: XfailList) -> Self {
+        TapParser {
+            expected_failures,
+            ..Default::default()
+        }
+    }
+
+    pub fn feed_line(&mut self, line: &str) {
+        let line = line.trim_end();
+        if self.bail_out.is_some() {
+            return;
+        }
+
+        if let Some(reason) = line.strip_prefix("Bail out!") {
+            self.bail_out = Some(reason.trim().to_string());
+        } else if let Some(plan) = line.strip_prefix("1..") {
+            let count = plan.split_whitespace().next().unwrap_or("");
+            if let Ok(count) = count.parse() {
+                self.plan = Some(count);
+            }
+        } else if let Some(rest) = line.strip_prefix("not ok") {
+            self.add_test(false, rest);
+        } else if let Some(rest) = line.strip_prefix("ok") {
+            self.add_test(true, rest);
+        }
+    }
+
+    fn add_test(&mut self, ok: bool, rest: &str) {
+        // "ok" must be followed by a separator, not be the start of a word
+        if !(rest.is_empty() || rest.starts_with(' ') || rest.starts_with('\t')) {
+            return;
+        }
+        let number = self.cases.len() + 1;
+
+        let (description, directive) = match rest.find('#') {
+            Some(pos) => (&rest[..pos], Some(rest[pos + 1..].trim())),
+            None => (rest, None),
+        };
+
+        // Strip the optional test number and "-" separator from the description
+        let description = description.trim_start();
+        let description = description.trim_start_matches(|c: char| c.is_ascii_digit());
+        let description = description.trim().trim_start_matches('-').trim();
+        let name = if description.is_empty() {
+            format!("test {}", number)
+        } else {
+            description.to_string()
+        };
+
+        let directive_word = |word: &str| {
+            directive
+                .filter(|d| d.len() >= word.len() && d[..word.len()].eq_ignore_ascii_case(word))
+                .map(|d| d[word.len()..].trim().to_string())
+        };
+
+        let (status, message) = if let Some(reason) = directive_word("skip") {
+            (TestStatus::Skipped, Some(reason))
+        } else if let Some(reason) = directive_word("todo") {
+            // TODO tests are expected to fail and never fail the run
+            self.todo += 1;
+            (TestStatus::Skipped, Some(format!("TODO {}", reason).trim_end().to_string()))
+        } else if ok {
+            (TestStatus::Passed, None)
+        } else {
+            (TestStatus::Failed, None)
+        };
+        let status = xfail::expected_status(&self.expected_failures, &name, status);
+
+        self.cases.push(TestCase {
+            name,
+            status,
+            duration: None,
+            message,
+        });
+    }
+
+    pub fn summary(&self) -> TapSummary {
+        let count = |status| self.cases.iter().filter(|case| case.status == status).count();
+        let xfail = count(TestStatus::ExpectedFailure);
+        let xpass = count(TestStatus::UnexpectedPass);
+        let ok = count(TestStatus::Passed) + xpass;
+        let not_ok = count(TestStatus::Failed);
+        let skip = count(TestStatus::Skipped) - self.todo;
+
+        let bail_out = self.bail_out.as_ref().map(|reason| format!("Bail out! {}", reason).trim_end().to_string());
+        let failing = (not_ok > 0).then(|| format!("{} test(s) not ok", not_ok));
+        let plan_problem = match self.plan {
+            None => Some("no TAP plan (1..N) found".to_string()),
+            Some(plan) if plan != self.cases.len() && self.bail_out.is_none() => {
+                Some(format!("planned {} test(s) but ran {}", plan, self.cases.len()))
+            }
+            Some(_) => None,
+        };
+        let problems = [bail_out.clone(), failing, plan_problem.clone()].into_iter().flatten().collect();
+        let incomplete = [bail_out, plan_problem].into_iter().flatten().collect();
+
+        TapSummary {
+            cases: self.cases.clone(),
+            ok,
+            not_ok,
+            skip,
+            todo: self.todo,
+            xfail,
+            xpass,
+            plan: self.plan,
+            problems,
+            incomplete,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> TapSummary {
+        let mut parser = TapParser::default();
+        for line in input.lines() {
+            parser.feed_line(line);
+        }
+        parser.summary()
+    }
+
+    #[test]
+    fn test_parse_passing_stream() {
+        let summary = parse(
+            "TAP version 13\n1..4\nok 1 - first\nok 2 second # SKIP no network\n\
+             not ok 3 - third # TODO not written yet\n    ok 1 - subtest\nok 4\n# done\n",
+        );
+        assert!(summary.is_success(), "{:?}", summary.problems);
+        assert_eq!((summary.ok, summary.not_ok, summary.skip, summary.todo), (2, 0, 1, 1));
+        assert_eq!(summary.cases[0].name, "first");
+        assert_eq!(summary.cases[1].name, "second");
+        assert_eq!(summary.cases[3].name, "test 4");
+    }
+
+    #[test]
+    fn test_parse_failures() {
+        let summary = parse("1..2\nok 1\nnot ok 2 - broken\n");
+        assert!(!summary.is_success());
+        assert_eq!(summary.not_ok, 1);
+        assert_eq!(summary.cases[1].status, TestStatus::Failed);
+    }
+
+    #[test]
+    fn test_parse_expected_failures() {
+        let mut parser = TapParser::expecting_failures(["known".to_string(), "fixed".to_string()].into());
+        for line in "1..3\nnot ok 1 - known\nok 2 - fixed\nok 3 - other\n".lines() {
+            parser.feed_line(line);
+        }
+        let summary = parser.summary();
+        assert!(summary.is_success(), "{:?}", summary.problems);
+        assert_eq!((summary.ok, summary.not_ok, summary.xfail, summary.xpass), (2, 0, 1, 1));
+        assert_eq!(summary.cases[0].status, TestStatus::ExpectedFailure);
+        assert_eq!(summary.cases[1].status, TestStatus::UnexpectedPass);
+    }
+
+    #[test]
+    fn test_parse_plan_problems() {
+        assert!(!parse("ok 1\nok 2\n").is_success());
+
+        let summary = parse("1..3\nok 1\nok 2\n");
+        assert_eq!(summary.problems, vec!["planned 3 test(s) but ran 2".to_string()]);
+
+        // A trailing plan is valid TAP
+        assert!(parse("ok 1\nok 2\n1..2\n").is_success());
+    }
+
+    #[test]
+    fn test_parse_bail_out() {
+        let summary = parse("1..3\nok 1\nBail out! database missing\nok 2\n");
+        assert_eq!(summary.cases.len(), 1);
+        assert_eq!(summary.problems, vec!["Bail out! database missing".to_string()]);
+
+        let summary = parse("1..3\nnot ok 1\nBail out! crashed\n");
+        assert_eq!(summary.problems, vec!["Bail out! crashed".to_string(), "1 test(s) not ok".to_string()]);
+        assert_eq!(summary.incomplete, vec!["Bail out! crashed".to_string()]);
+    }
+}
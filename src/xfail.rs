@@ -0,0 +1,93 @@
+use crate::results::{TestCase, TestStatus};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// File listing the expected failures, one test name per line, inside the feature's directory
+const XFAIL_FILE: &str = "xfail.txt";
+
+/// Names of the tests of a feature that are known to fail
+pub type XfailList = BTreeSet<String>;
+
+/// Path of a feature's xfail list: `<project_root>/.c2rust/<feature>/xfail.txt`
+pub fn xfail_path(project_root: &Path, feature: &str) -> PathBuf {
+    project_root.join(".c2rust").join(feature).join(XFAIL_FILE)
+}
+
+/// Read a feature's xfail list; empty if it has none. Blank lines and `#` comments are ignored.
+pub fn load(project_root: &Path, feature: &str) -> std::io::Result<XfailList> {
+    match std::fs::read_to_string(xfail_path(project_root, feature)) {
+        Ok(content) => Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(XfailList::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write a feature's xfail list, sorted; an empty list removes the file
+pub fn save(project_root: &Path, feature: &str, list: &XfailList) -> std::io::Result<()> {
+    let path = xfail_path(project_root, feature);
+    if list.is_empty() {
+        return match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content: String = list.iter().map(|name| format!("{}\n", name)).collect();
+    std::fs::write(path, content)
+}
+
+/// Status of a test once the xfail list is taken into account: a listed test
+/// that fails is an expected failure, one that passes is an unexpected pass
+pub fn expected_status(list: &XfailList, name: &str, status: TestStatus) -> TestStatus {
+    match status {
+        TestStatus::Failed if list.contains(name) => TestStatus::ExpectedFailure,
+        TestStatus::Passed if list.contains(name) => TestStatus::UnexpectedPass,
+        status => status,
+    }
+}
+
+/// Mark the expected failures and unexpected passes among parsed test cases
+pub fn apply(list: &XfailList, cases: &mut [TestCase]) {
+    for case in cases {
+        case.status = expected_status(list, &case.name, case.status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(load(temp_dir.path(), "f1").unwrap().is_empty());
+
+        let list: XfailList = ["b".to_string(), "a".to_string()].into();
+        save(temp_dir.path(), "f1", &list).unwrap();
+        let path = xfail_path(temp_dir.path(), "f1");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\nb\n");
+
+        std::fs::write(&path, "# known failures\na\n\n  b  \n").unwrap();
+        assert_eq!(load(temp_dir.path(), "f1").unwrap(), list);
+
+        save(temp_dir.path(), "f1", &XfailList::new()).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_expected_status() {
+        let list: XfailList = ["known".to_string()].into();
+        assert_eq!(expected_status(&list, "known", TestStatus::Failed), TestStatus::ExpectedFailure);
+        assert_eq!(expected_status(&list, "known", TestStatus::Passed), TestStatus::UnexpectedPass);
+        assert_eq!(expected_status(&list, "known", TestStatus::Skipped), TestStatus::Skipped);
+        assert_eq!(expected_status(&list, "other", TestStatus::Failed), TestStatus::Failed);
+    }
+}
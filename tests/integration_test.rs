@@ -1,4 +1,4 @@
-use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
 use predicates::prelude::*;
 use tempfile::TempDir;
 
@@ -6,6 +6,7 @@ use tempfile::TempDir;
 fn setup_mock_c2rust_config() -> std::path::PathBuf {
     let mock_script = r#"#!/bin/bash
 # Mock c2rust-config for testing purposes
+# Values are stored as one file per key under .c2rust/mock-config/<feature>/
 
 case "$1" in
   --help)
@@ -13,7 +14,29 @@ case "$1" in
     exit 0
     ;;
   config)
-    # Mock config command - just succeed
+    shift
+    feature=default
+    action=""
+    while [ $# -gt 0 ]; do
+      case "$1" in
+        --feature) feature="$2"; shift 2 ;;
+        --set) action=set; key="$2"; value="$3"; shift 3 ;;
+        --get) action=get; key="$2"; shift 2 ;;
+        *) shift ;;
+      esac
+    done
+    store=".c2rust/mock-config/$feature"
+    case "$action" in
+      set)
+        mkdir -p "$store"
+        printf '%s' "$value" > "$store/$key"
+        ;;
+      get)
+        [ -f "$store/$key" ] || exit 1
+        cat "$store/$key"
+        echo
+        ;;
+    esac
     exit 0
     ;;
   *)
@@ -44,12 +67,23 @@ esac
     mock_path
 }
 
+// Helper function to create the .c2rust git repository with a commit identity
+fn init_c2rust_repo(temp_dir: &TempDir) -> (std::path::PathBuf, git2::Repository) {
+    let c2rust_dir = temp_dir.path().join(".c2rust");
+    std::fs::create_dir(&c2rust_dir).unwrap();
+    let repo = git2::Repository::init(&c2rust_dir).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("user.name", "Test User").unwrap();
+    config.set_str("user.email", "test@example.com").unwrap();
+    (c2rust_dir, repo)
+}
+
 #[test]
 fn test_test_command_basic() {
     let temp_dir = TempDir::new().unwrap();
     let mock_config = setup_mock_c2rust_config();
 
-    let mut cmd = Command::cargo_bin("c2rust-test").unwrap();
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
 
     // Set the current directory for the command to run in
     cmd.current_dir(temp_dir.path())
@@ -64,7 +98,7 @@ fn test_test_command_basic() {
 
 #[test]
 fn test_missing_command_argument() {
-    let mut cmd = Command::cargo_bin("c2rust-test").unwrap();
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
 
     cmd.arg("test");
 
@@ -76,7 +110,7 @@ fn test_missing_command_argument() {
 
 #[test]
 fn test_help_output() {
-    let mut cmd = Command::cargo_bin("c2rust-test").unwrap();
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
 
     cmd.arg("--help");
 
@@ -88,7 +122,7 @@ fn test_help_output() {
 
 #[test]
 fn test_test_subcommand_help() {
-    let mut cmd = Command::cargo_bin("c2rust-test").unwrap();
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
 
     cmd.arg("test").arg("--help");
 
@@ -103,7 +137,7 @@ fn test_with_separator() {
     let temp_dir = TempDir::new().unwrap();
     let mock_config = setup_mock_c2rust_config();
 
-    let mut cmd = Command::cargo_bin("c2rust-test").unwrap();
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
 
     // Set the current directory for the command to run in
     cmd.current_dir(temp_dir.path())
@@ -121,7 +155,7 @@ fn test_command_with_hyphen() {
     let temp_dir = TempDir::new().unwrap();
     let mock_config = setup_mock_c2rust_config();
 
-    let mut cmd = Command::cargo_bin("c2rust-test").unwrap();
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
 
     // Test that we can pass arguments starting with hyphen after --
     // Set the current directory for the command to run in
@@ -141,7 +175,7 @@ fn test_with_feature_flag() {
     let temp_dir = TempDir::new().unwrap();
     let mock_config = setup_mock_c2rust_config();
 
-    let mut cmd = Command::cargo_bin("c2rust-test").unwrap();
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
 
     cmd.current_dir(temp_dir.path())
         .env("C2RUST_CONFIG", &mock_config)
@@ -155,11 +189,35 @@ fn test_with_feature_flag() {
     cmd.assert().success().stdout(predicate::str::contains("Feature: custom"));
 }
 
+#[test]
+fn test_feature_must_be_a_single_directory_name() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("project");
+    std::fs::create_dir(&project).unwrap();
+    let mock_config = setup_mock_c2rust_config();
+
+    for feature in ["../escaped", "a/b", "..", ""] {
+        let mut cmd = cargo_bin_cmd!("c2rust-test");
+        cmd.current_dir(&project)
+            .env("C2RUST_CONFIG", &mock_config)
+            .args(["test", "--feature", feature, "--", "echo", "test"]);
+        cmd.assert()
+            .failure()
+            .code(2)
+            .stderr(predicate::str::contains("invalid feature name"));
+    }
+    assert!(!temp_dir.path().join("escaped").exists());
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(&project).args(["xfail", "add", "--feature", "../escaped", "a"]);
+    cmd.assert().failure().stderr(predicate::str::contains("invalid feature name"));
+}
+
 #[test]
 fn test_without_c2rust_config() {
     let temp_dir = TempDir::new().unwrap();
 
-    let mut cmd = Command::cargo_bin("c2rust-test").unwrap();
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
 
     // Don't set C2RUST_CONFIG, so it should fail
     cmd.current_dir(temp_dir.path())
@@ -175,3 +233,991 @@ fn test_without_c2rust_config() {
 }
 
 
+#[test]
+fn test_output_is_written_to_log() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    std::fs::create_dir(temp_dir.path().join(".c2rust")).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .arg("test")
+        .arg("--feature")
+        .arg("logged")
+        .arg("--")
+        .arg("echo")
+        .arg("hello-from-test");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("hello-from-test"))
+        .stdout(predicate::str::contains("Log file:"));
+
+    let log_dir = temp_dir.path().join(".c2rust").join("logged").join("test-logs");
+    let logs: Vec<_> = std::fs::read_dir(&log_dir).unwrap().collect();
+    assert_eq!(logs.len(), 1);
+    let content = std::fs::read_to_string(logs[0].as_ref().unwrap().path()).unwrap();
+    assert!(content.contains("hello-from-test"));
+}
+
+#[test]
+fn test_background_process_holding_output_does_not_block() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--", "sh", "-c", "sleep 6 & echo started"]);
+
+    let started = std::time::Instant::now();
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("started"))
+        .stdout(predicate::str::contains("Exit code: 0"));
+    assert!(started.elapsed() < std::time::Duration::from_secs(4), "took {:?}", started.elapsed());
+}
+
+#[test]
+fn test_timeout_exit_code() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .arg("test")
+        .arg("--timeout")
+        .arg("300ms")
+        .arg("--")
+        .arg("sleep")
+        .arg("30");
+
+    cmd.assert()
+        .code(124)
+        .stderr(predicate::str::contains("timed out"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sigterm_is_forwarded_to_test_command() {
+    use std::time::{Duration, Instant};
+
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_c2rust-test"))
+        .current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--", "sleep", "30"])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // Give c2rust-test time to start the test command
+    std::thread::sleep(Duration::from_millis(500));
+    let kill = std::process::Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(kill.success());
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            panic!("c2rust-test did not exit after SIGTERM");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+
+    // sleep was killed by the forwarded SIGTERM: 128 + 15
+    assert_eq!(status.code(), Some(143));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_stopped_test_command_stops_and_resumes_with_c2rust_test() {
+    use std::time::{Duration, Instant};
+
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+
+    // The command stops itself the way Ctrl-Z would stop its process group
+    let child = std::process::Command::new(env!("CARGO_BIN_EXE_c2rust-test"))
+        .current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--", "sh", "-c", "kill -TSTP $$; echo resumed"])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // c2rust-test stops itself too, so that a shell would show a stopped job
+    let state_path = format!("/proc/{}/status", child.id());
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !std::fs::read_to_string(&state_path).unwrap().contains("(stopped)") {
+        assert!(Instant::now() < deadline, "c2rust-test did not stop with the test command");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    // Resuming c2rust-test (as `fg` or `bg` would) resumes the test command
+    let cont = std::process::Command::new("kill")
+        .args(["-CONT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(cont.success());
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("resumed"));
+}
+
+#[test]
+fn test_run_replays_saved_command() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    std::fs::create_dir(temp_dir.path().join(".c2rust")).unwrap();
+    let sub_dir = temp_dir.path().join("tests");
+    std::fs::create_dir(&sub_dir).unwrap();
+
+    // Record the command from a subdirectory of the project
+    let mut record = cargo_bin_cmd!("c2rust-test");
+    record
+        .current_dir(&sub_dir)
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--feature", "replay", "--", "pwd"]);
+    record.assert().success();
+
+    // Replay it from the project root: it must run in the recorded directory
+    let mut replay = cargo_bin_cmd!("c2rust-test");
+    replay
+        .current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["run", "--feature", "replay"]);
+    replay
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Test directory (relative): tests"))
+        .stdout(predicate::str::contains("Command: pwd"));
+}
+
+#[test]
+fn test_run_without_saved_command() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["run", "--feature", "missing"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("test.dir is not set"));
+}
+
+#[test]
+fn test_junit_summary() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+
+    let script = r#"cat > results.xml <<'XML'
+<testsuite name="s">
+  <testcase classname="s" name="good"/>
+  <testcase classname="s" name="bad"><failure message="boom"/></testcase>
+</testsuite>
+XML
+exit 1"#;
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--junit", "*.xml", "--", "sh", "-c", script]);
+
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("PASS  s.good"))
+        .stdout(predicate::str::contains("FAIL  s.bad: boom"))
+        .stdout(predicate::str::contains("2 tests: 1 passed, 1 failed, 0 skipped"));
+}
+
+#[test]
+fn test_tap_failure_fails_run_with_zero_exit() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--format", "tap", "--", "printf", "1..2\\nok 1 - a\\nnot ok 2 - b\\n"]);
+
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("TAP: 1 ok, 1 not ok, 0 skip, 0 todo (plan 1..2)"))
+        .stderr(predicate::str::contains("Tests failed"));
+}
+
+#[test]
+fn test_tap_success() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--format", "tap", "--", "printf", "1..2\\nok 1\\nok 2 # SKIP later\\n"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("TAP: 1 ok, 0 not ok, 1 skip, 0 todo"));
+}
+
+#[test]
+fn test_json_report() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--feature", "reported", "--report", "out/report.json", "--", "sh", "-c", "exit 7"]);
+
+    cmd.assert().code(7);
+
+    let report = std::fs::read_to_string(temp_dir.path().join("out/report.json")).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&report).unwrap();
+    assert_eq!(json["feature"], "reported");
+    assert_eq!(json["test_dir"], ".");
+    assert_eq!(json["argv"], serde_json::json!(["sh", "-c", "exit 7"]));
+    assert_eq!(json["exit_code"], 7);
+    assert_eq!(json["success"], false);
+    assert_eq!(json["config_saved"], false);
+    assert!(json["started_at"].is_string());
+    assert!(json["duration_secs"].is_number());
+
+    // The report keeps the command's real exit code when the run passes regardless
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--feature", "reported", "--format", "tap", "--fail-on", "regressions"])
+        .args(["--report", "out/report.json", "--", "sh", "-c", "printf '1..1\\nnot ok 1 - a\\n'; exit 3"]);
+    cmd.assert().success();
+
+    let report = std::fs::read_to_string(temp_dir.path().join("out/report.json")).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&report).unwrap();
+    assert_eq!(json["exit_code"], 3);
+    assert_eq!(json["success"], true);
+}
+
+#[test]
+fn test_env_is_applied_and_replayed() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let mut record = cargo_bin_cmd!("c2rust-test");
+    record
+        .current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .env_remove("GREETING")
+        .args(["test", "--env", "GREETING=hello world", "--", "printenv", "GREETING"]);
+    record
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Environment: GREETING=hello world"));
+
+    let stored = std::fs::read_to_string(temp_dir.path().join(".c2rust/mock-config/default/test.env")).unwrap();
+    assert_eq!(stored, r#"{"GREETING":"hello world"}"#);
+
+    let mut replay = cargo_bin_cmd!("c2rust-test");
+    replay
+        .current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .env_remove("GREETING")
+        .args(["run"]);
+    replay
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hello world"));
+}
+
+#[test]
+fn test_argv_with_spaces_round_trips() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let check = r#"test "$1" = "a b" && test $# -eq 1"#;
+
+    let mut record = cargo_bin_cmd!("c2rust-test");
+    record
+        .current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--", "sh", "-c", check, "_", "a b"]);
+    record.assert().success();
+
+    let store = temp_dir.path().join(".c2rust/mock-config/default");
+    let cmd = std::fs::read_to_string(store.join("test.cmd")).unwrap();
+    assert!(cmd.ends_with(" _ 'a b'"), "unexpected test.cmd: {}", cmd);
+    let argv: Vec<String> = serde_json::from_str(&std::fs::read_to_string(store.join("test.argv")).unwrap()).unwrap();
+    assert_eq!(argv, vec!["sh", "-c", check, "_", "a b"]);
+
+    let mut replay = cargo_bin_cmd!("c2rust-test");
+    replay
+        .current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["run"]);
+    replay.assert().success();
+
+    // Configurations without test.argv fall back to splitting test.cmd
+    std::fs::remove_file(store.join("test.argv")).unwrap();
+    let mut legacy = cargo_bin_cmd!("c2rust-test");
+    legacy
+        .current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["run"]);
+    legacy.assert().success();
+}
+
+#[test]
+fn test_shell_mode_is_recorded_and_replayed() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+
+    let mut record = cargo_bin_cmd!("c2rust-test");
+    record
+        .current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .env("SHELL", "/bin/sh")
+        .args(["test", "--shell", "--", "echo hi | tr a-z A-Z > out.txt && grep -q HI out.txt"]);
+    record
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Shell: /bin/sh"));
+
+    let store = temp_dir.path().join(".c2rust/mock-config/default");
+    assert_eq!(std::fs::read_to_string(store.join("test.shell")).unwrap(), "true");
+    assert_eq!(std::fs::read_to_string(store.join("test.shell_path")).unwrap(), "/bin/sh");
+
+    std::fs::remove_file(temp_dir.path().join("out.txt")).unwrap();
+    let mut replay = cargo_bin_cmd!("c2rust-test");
+    replay
+        .current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .env("SHELL", "/nonexistent/shell")
+        .args(["run"]);
+    replay.assert().success();
+    assert!(temp_dir.path().join("out.txt").exists());
+}
+
+#[test]
+fn test_shell_mode_rejects_several_arguments() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--shell", "--", "echo", "a  b"]);
+
+    cmd.assert()
+        .code(2)
+        .stderr(predicate::str::contains("single argument"));
+    assert!(!temp_dir.path().join(".c2rust/mock-config").exists());
+}
+
+#[test]
+fn test_dry_run_executes_and_writes_nothing() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let c2rust_dir = temp_dir.path().join(".c2rust");
+    std::fs::create_dir(&c2rust_dir).unwrap();
+    git2::Repository::init(&c2rust_dir).unwrap();
+    std::fs::write(c2rust_dir.join("pending.txt"), "x").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--dry-run", "--", "touch", "marker file"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Would execute: touch 'marker file'"))
+        .stdout(predicate::str::contains("--set test.cmd 'touch '\\''marker file'\\'''"))
+        .stdout(predicate::str::contains("1 pending change(s) that would be left alone"))
+        .stdout(predicate::str::contains("pending.txt"));
+
+    assert!(!temp_dir.path().join("marker file").exists());
+    assert!(!c2rust_dir.join("mock-config").exists());
+    assert!(!c2rust_dir.join("default").exists());
+}
+
+#[test]
+fn test_record_on_failure_saves_config_and_exit_code() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let store = temp_dir.path().join(".c2rust/mock-config/default");
+
+    // Without the flag a failing run saves nothing
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--", "sh", "-c", "exit 3"]);
+    cmd.assert().failure().code(3);
+    assert!(!store.join("test.cmd").exists());
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--record-on-failure", "--", "sh", "-c", "exit 3"]);
+    cmd.assert()
+        .failure()
+        .code(3)
+        .stdout(predicate::str::contains("Configuration saved (test.last_status=fail)"));
+
+    assert_eq!(std::fs::read_to_string(store.join("test.cmd")).unwrap(), "sh -c 'exit 3'");
+    assert_eq!(std::fs::read_to_string(store.join("test.last_status")).unwrap(), "fail");
+    assert_eq!(std::fs::read_to_string(store.join("test.last_exit_code")).unwrap(), "3");
+
+    // A passing run overwrites the recorded status
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--record-on-failure", "--", "true"]);
+    cmd.assert().success();
+    assert_eq!(std::fs::read_to_string(store.join("test.last_status")).unwrap(), "pass");
+    assert_eq!(std::fs::read_to_string(store.join("test.last_exit_code")).unwrap(), "0");
+}
+
+#[test]
+fn test_auto_commit_message_describes_run() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let (_, repo) = init_c2rust_repo(&temp_dir);
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--feature", "f1", "--", "echo", "hi"]);
+    cmd.assert().success();
+
+    let commit = repo.head().unwrap().peel_to_commit().unwrap();
+    let message = commit.message().unwrap();
+    assert!(message.starts_with("c2rust-test test [f1]: PASS echo hi ("), "{}", message);
+    assert!(message.contains("C2rust-Feature: f1\n"));
+    assert!(message.contains("C2rust-Exit-Code: 0\n"));
+    assert!(message.contains("C2rust-Test-Dir: .\n"));
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["run", "--feature", "f1", "--message", "Nightly replay"]);
+    cmd.assert().success();
+
+    let commit = repo.head().unwrap().peel_to_commit().unwrap();
+    let message = commit.message().unwrap();
+    assert!(message.starts_with("Nightly replay\n\n"), "{}", message);
+    assert!(message.contains("C2rust-Subcommand: run\n"));
+}
+
+#[test]
+fn test_commit_policy_controls_auto_commit() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let (c2rust_dir, repo) = init_c2rust_repo(&temp_dir);
+
+    let run = |args: &[&str], no_commit_env: Option<&str>| {
+        let mut cmd = cargo_bin_cmd!("c2rust-test");
+        cmd.current_dir(temp_dir.path()).env("C2RUST_CONFIG", &mock_config).args(args);
+        match no_commit_env {
+            Some(value) => cmd.env("C2RUST_TEST_NO_COMMIT", value),
+            None => cmd.env_remove("C2RUST_TEST_NO_COMMIT"),
+        };
+        cmd.assert()
+    };
+
+    run(&["test", "--no-commit", "--", "true"], None)
+        .success()
+        .stdout(predicate::str::contains("Auto-commit skipped (--no-commit)."));
+    assert!(repo.head().is_err());
+
+    run(&["test", "--", "true"], Some("1"))
+        .success()
+        .stdout(predicate::str::contains("Auto-commit skipped (C2RUST_TEST_NO_COMMIT)."));
+    assert!(repo.head().is_err());
+
+    // The default policy commits passing runs only
+    run(&["test", "--", "true"], Some("0")).success();
+    let first = repo.head().unwrap().peel_to_commit().unwrap().id();
+    run(&["run"], None).success();
+    let second = repo.head().unwrap().peel_to_commit().unwrap().id();
+    assert_ne!(first, second);
+
+    let store = c2rust_dir.join("mock-config/default");
+    std::fs::write(store.join("test.cmd"), "false").unwrap();
+    std::fs::remove_file(store.join("test.argv")).unwrap();
+    run(&["run"], None).failure();
+    assert_eq!(repo.head().unwrap().peel_to_commit().unwrap().id(), second);
+
+    // "always" also commits the log of a failing run
+    std::fs::write(store.join("test.commit_policy"), "always").unwrap();
+    run(&["run"], None).failure();
+    let commit = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_ne!(commit.id(), second);
+    assert!(commit.message().unwrap().contains("C2rust-Status: fail\n"));
+
+    std::fs::write(store.join("test.commit_policy"), "never").unwrap();
+    run(&["run"], None)
+        .failure()
+        .stdout(predicate::str::contains("Auto-commit skipped (test.commit_policy)."));
+    assert_eq!(repo.head().unwrap().peel_to_commit().unwrap().id(), commit.id());
+}
+
+#[test]
+fn test_tag_baseline_and_list() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let (_, repo) = init_c2rust_repo(&temp_dir);
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["baseline", "list"]);
+    cmd.assert().success().stdout(predicate::str::contains("No baselines found."));
+
+    // A failing run is never tagged
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--record-on-failure", "--tag-baseline", "--", "false"]);
+    cmd.assert().failure();
+    assert_eq!(repo.tag_names(None).unwrap().len(), 0);
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--feature", "f1", "--tag-baseline", "annotated", "--", "true"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Tagged baseline test-pass/f1/"));
+
+    let head = repo.head().unwrap().peel_to_commit().unwrap().id();
+    let tags = repo.tag_names(Some("test-pass/f1/*")).unwrap();
+    assert_eq!(tags.len(), 1);
+    let tag = repo.revparse_single(&format!("refs/tags/{}", tags.get(0).unwrap())).unwrap();
+    assert!(tag.as_tag().is_some(), "expected an annotated tag");
+    assert_eq!(tag.peel_to_commit().unwrap().id(), head);
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["baseline", "list", "--feature", "f1"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("test-pass/f1/"))
+        .stdout(predicate::str::contains("c2rust-test test [f1]: PASS true"));
+}
+
+#[test]
+fn test_auto_commit_without_git_identity_uses_fallback() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let c2rust_dir = temp_dir.path().join(".c2rust");
+    std::fs::create_dir(&c2rust_dir).unwrap();
+    let repo = git2::Repository::init(&c2rust_dir).unwrap();
+
+    // No user.name/user.email anywhere git looks
+    let run = |args: &[&str], author: Option<(&str, &str)>| {
+        let mut cmd = cargo_bin_cmd!("c2rust-test");
+        cmd.current_dir(temp_dir.path())
+            .env("C2RUST_CONFIG", &mock_config)
+            .env("HOME", home.path())
+            .env("XDG_CONFIG_HOME", home.path())
+            .env_remove("GIT_AUTHOR_NAME")
+            .env_remove("GIT_AUTHOR_EMAIL")
+            .env_remove("GIT_COMMITTER_NAME")
+            .env_remove("GIT_COMMITTER_EMAIL")
+            .args(args);
+        match author {
+            Some((name, email)) => cmd.env("C2RUST_TEST_AUTHOR_NAME", name).env("C2RUST_TEST_AUTHOR_EMAIL", email),
+            None => cmd.env_remove("C2RUST_TEST_AUTHOR_NAME").env_remove("C2RUST_TEST_AUTHOR_EMAIL"),
+        };
+        cmd.assert().success();
+        repo.head().unwrap().peel_to_commit().unwrap()
+    };
+
+    let commit = run(&["test", "--", "true"], None);
+    assert_eq!(commit.author().name(), Some("c2rust-test"));
+    assert_eq!(commit.author().email(), Some("c2rust-test@localhost"));
+
+    let store = c2rust_dir.join("mock-config/default");
+    std::fs::write(store.join("test.author_name"), "Config Bot").unwrap();
+    std::fs::write(store.join("test.author_email"), "bot@example.com").unwrap();
+    let commit = run(&["run"], None);
+    assert_eq!(commit.author().name(), Some("Config Bot"));
+    assert_eq!(commit.author().email(), Some("bot@example.com"));
+
+    let commit = run(&["run"], Some(("CI", "ci@example.com")));
+    assert_eq!(commit.author().name(), Some("CI"));
+    assert_eq!(commit.committer().email(), Some("ci@example.com"));
+}
+
+#[test]
+fn test_history_lists_recorded_runs() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let (_, repo) = init_c2rust_repo(&temp_dir);
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path()).args(["history"]);
+    cmd.assert().success().stdout(predicate::str::contains("No test runs recorded."));
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--feature", "f1", "--", "echo", "one"]);
+    cmd.assert().success();
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--feature", "f2", "--record-on-failure", "--", "sh", "-c", "exit 4"]);
+    cmd.assert().failure();
+    let head = repo.head().unwrap().peel_to_commit().unwrap().id().to_string();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path()).args(["history"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", output);
+    assert!(lines[0].contains(&head[..10]));
+    assert!(lines[0].contains("FAIL  [f2]  test sh -c 'exit 4'"), "{}", lines[0]);
+    assert!(lines[0].contains("exit 4)"));
+    assert!(lines[1].contains("PASS  [f1]  test echo one"), "{}", lines[1]);
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path()).args(["history", "--feature", "f1", "--limit", "5"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("[f1]"))
+        .stdout(predicate::str::contains("[f2]").not());
+}
+
+#[test]
+fn test_lock_timeout_fails_fast_when_c2rust_is_locked() {
+    use std::os::unix::io::AsRawFd;
+
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let (c2rust_dir, repo) = init_c2rust_repo(&temp_dir);
+
+    // Another invocation holds the lock
+    let held = std::fs::File::create(c2rust_dir.join(".lock")).unwrap();
+    assert_eq!(unsafe { libc::flock(held.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) }, 0);
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--lock-timeout", "0", "--", "true"]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("Failed to lock .c2rust"));
+    assert!(!c2rust_dir.join("mock-config/default/test.cmd").exists());
+
+    drop(held);
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--lock-timeout", "0", "--", "true"]);
+    cmd.assert().success();
+
+    // The lock file itself is never committed
+    let tree = repo.head().unwrap().peel_to_commit().unwrap().tree().unwrap();
+    assert!(tree.get_name(".lock").is_none());
+    assert!(tree.get_path(std::path::Path::new("mock-config/default/test.cmd")).is_ok());
+}
+
+#[test]
+fn test_diff_compares_c_and_rust_builds() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let store = temp_dir.path().join(".c2rust/mock-config/default");
+    std::fs::create_dir_all(&store).unwrap();
+    std::fs::create_dir(temp_dir.path().join("c-build")).unwrap();
+    std::fs::create_dir(temp_dir.path().join("rust-build")).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["diff"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("test.dir is not set"));
+
+    std::fs::write(store.join("test.dir"), ".").unwrap();
+    std::fs::write(
+        store.join("test.argv"),
+        r#"["sh","-c","echo one; echo two; echo three; echo \"impl $IMPL\"; exit ${CODE:-0}"]"#,
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["diff"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("no C/Rust builds configured"));
+
+    // Both sides print the same thing from different directories
+    std::fs::write(store.join("test.c.dir"), "c-build").unwrap();
+    std::fs::write(store.join("test.rust.dir"), "rust-build").unwrap();
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["diff"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("=== C build ==="))
+        .stdout(predicate::str::contains("=== Rust build ==="))
+        .stdout(predicate::str::contains("C and Rust builds behave identically"));
+
+    std::fs::write(store.join("test.c.env"), r#"{"IMPL":"c"}"#).unwrap();
+    std::fs::write(store.join("test.rust.env"), r#"{"IMPL":"rust","CODE":"3"}"#).unwrap();
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["diff"]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("Divergence: exit code 0 (C) vs 3 (Rust)"))
+        .stdout(predicate::str::contains("Divergence: stdout differs at line 4:"))
+        .stdout(predicate::str::contains("             3 | three"))
+        .stdout(predicate::str::contains("  C          4 | impl c"))
+        .stdout(predicate::str::contains("  Rust       4 | impl rust"))
+        .stderr(predicate::str::contains("C and Rust builds diverge"));
+}
+
+#[test]
+fn test_record_and_check_golden_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let (c2rust_dir, repo) = init_c2rust_repo(&temp_dir);
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["run", "--check-golden"]);
+    cmd.assert().failure();
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--record-golden", "--", "sh", "-c", "echo 'sum: 3'; echo note >&2"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Golden output recorded in"));
+
+    let golden_dir = c2rust_dir.join("default/golden");
+    assert_eq!(std::fs::read_to_string(golden_dir.join("stdout.txt")).unwrap(), "sum: 3\n");
+    assert_eq!(std::fs::read_to_string(golden_dir.join("stderr.txt")).unwrap(), "note\n");
+    let tree = repo.head().unwrap().peel_to_commit().unwrap().tree().unwrap();
+    assert!(tree.get_path(std::path::Path::new("default/golden/stdout.txt")).is_ok());
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["run", "--check-golden"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Output matches the golden output."));
+
+    // The command still exits 0, but prints a different result
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--check-golden", "--", "sh", "-c", "echo 'sum: 4'; echo note >&2"]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("Divergence: stdout differs at line 1:"))
+        .stdout(predicate::str::contains("  Golden     1 | sum: 3"))
+        .stdout(predicate::str::contains("  Actual     1 | sum: 4"))
+        .stderr(predicate::str::contains("output differs from the golden output"));
+    assert_eq!(std::fs::read_to_string(golden_dir.join("stdout.txt")).unwrap(), "sum: 3\n");
+}
+
+#[test]
+fn test_normalize_rules_apply_to_golden_check() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let script = "echo \"node at $1 freed\"; echo \"wrote $2\"";
+
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--no-commit", "--record-golden", "--", "sh", "-c", script])
+        .args(["sh", "0x7ffd5e8c1a20", "/tmp/tmp.Xa81/out.txt"]);
+    cmd.assert().success();
+
+    // Addresses and temporary paths differ from run to run
+    let rerun = || {
+        let mut cmd = cargo_bin_cmd!("c2rust-test");
+        cmd.current_dir(temp_dir.path())
+            .env("C2RUST_CONFIG", &mock_config)
+            .args(["test", "--no-commit", "--check-golden", "--", "sh", "-c", script])
+            .args(["sh", "0x55d0c3a4b2f0", "/tmp/tmp.Q3z7/out.txt"]);
+        cmd
+    };
+    rerun()
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Divergence: stdout differs at line 1:"));
+
+    let store = temp_dir.path().join(".c2rust/mock-config/default");
+    std::fs::write(store.join("test.normalize"), r#"["hex-addresses", "tmp-paths"]"#).unwrap();
+    rerun()
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Output matches the golden output."));
+
+    std::fs::write(store.join("test.normalize"), r#"["no-such-preset"]"#).unwrap();
+    rerun()
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("test.normalize: unknown preset 'no-such-preset'"));
+}
+
+#[test]
+fn test_fail_on_regressions() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let run = |tap: &str| {
+        let mut cmd = cargo_bin_cmd!("c2rust-test");
+        cmd.current_dir(temp_dir.path())
+            .env("C2RUST_CONFIG", &mock_config)
+            .args(["test", "--format", "tap", "--fail-on", "regressions", "--", "sh", "-c"])
+            .arg(format!("printf '{}'; exit 1", tap));
+        cmd
+    };
+
+    // b has never passed, so its failure is not a regression
+    run("1..2\\nok 1 - a\\nnot ok 2 - b\\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No previous test results"))
+        .stdout(predicate::str::contains("Test command failed, but no previously passing test fails"));
+    let stored = std::fs::read_to_string(temp_dir.path().join(".c2rust/default/results.json")).unwrap();
+    assert!(stored.contains(r#""a": "passed""#));
+    assert!(stored.contains(r#""b": "failed""#));
+
+    run("1..3\\nok 1 - a\\nnot ok 2 - b\\nnot ok 3 - c\\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("  NEW            c"))
+        .stdout(predicate::str::contains(
+            "3 tests: 0 newly failing, 0 newly passing, 1 still failing, 1 still passing, 1 new",
+        ));
+
+    run("1..3\\nnot ok 1 - a\\nok 2 - b\\nnot ok 3 - c\\n")
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("  NEWLY FAILING  a"))
+        .stdout(predicate::str::contains("  NEWLY PASSING  b"))
+        .stderr(predicate::str::contains("1 previously passing test(s) now fail: a"));
+
+    // A crash ends the stream early: the tests it never reached are regressions too
+    run("1..3\\nok 1 - a\\nBail out! harness crashed\\n")
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("  MISSING        b"))
+        .stdout(predicate::str::contains("1 tests: 0 newly failing, 1 newly passing, 0 still failing, 0 still passing, 0 new, 1 missing"))
+        .stderr(predicate::str::contains("Bail out! harness crashed"))
+        .stderr(predicate::str::contains("1 previously passing test(s) did not run: b"));
+}
+
+#[test]
+fn test_xfail_list_and_expected_failures() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let (c2rust_dir, repo) = init_c2rust_repo(&temp_dir);
+
+    // Editing the list needs no c2rust-config; with a git identity, no fallback is looked up
+    let xfail = |args: &[&str]| {
+        let mut cmd = cargo_bin_cmd!("c2rust-test");
+        cmd.current_dir(temp_dir.path())
+            .env("C2RUST_CONFIG", temp_dir.path().join("no-such-c2rust-config"))
+            .arg("xfail")
+            .args(args);
+        cmd
+    };
+    xfail(&["list"]).assert().success().stdout(predicate::str::contains("No expected failures for default."));
+    xfail(&["add", "b", "c"]).assert().success().stdout(predicate::str::contains("✓ Added b")).stderr("");
+    xfail(&["add", "b"]).assert().success().stdout(predicate::str::contains("b is already in the xfail list"));
+    xfail(&["list"]).assert().success().stdout("b\nc\n");
+
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.summary(), Some("c2rust-test xfail [default]: xfail add b c"));
+    assert_eq!(std::fs::read_to_string(c2rust_dir.join("default/xfail.txt")).unwrap(), "b\nc\n");
+
+    let run = |tap: &str| {
+        let mut cmd = cargo_bin_cmd!("c2rust-test");
+        cmd.current_dir(temp_dir.path())
+            .env("C2RUST_CONFIG", &mock_config)
+            .args(["test", "--format", "tap", "--", "sh", "-c"])
+            .arg(format!("printf '{}'; exit 1", tap));
+        cmd
+    };
+
+    // The only failing test is expected to fail, so the exit code is ignored
+    run("1..3\\nok 1 - a\\nnot ok 2 - b\\nok 3 - c\\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("  XFAIL  b"))
+        .stdout(predicate::str::contains("  XPASS  c"))
+        .stdout(predicate::str::contains("TAP: 2 ok, 0 not ok, 0 skip, 0 todo, 1 xfail, 1 xpass"))
+        .stdout(predicate::str::contains("XPASS: 1 test(s) in the xfail list now pass: c"))
+        .stdout(predicate::str::contains("Remove them with: c2rust-test xfail remove --feature default c"))
+        .stdout(predicate::str::contains("all failing tests are expected failures (xfail)"));
+
+    run("1..3\\nnot ok 1 - a\\nnot ok 2 - b\\nok 3 - c\\n")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("TAP failure: 1 test(s) not ok"));
+
+    xfail(&["remove", "c", "d"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("✓ Removed c"))
+        .stdout(predicate::str::contains("d is not in the xfail list of default."));
+    xfail(&["list"]).assert().success().stdout("b\n");
+}
+
+#[test]
+fn test_auto_commit_skips_logs_of_concurrent_runs() {
+    let temp_dir = TempDir::new().unwrap();
+    let mock_config = setup_mock_c2rust_config();
+    let (_, repo) = init_c2rust_repo(&temp_dir);
+
+    // Stands in for 'c2rust-test test --feature fb' writing its log at the same time
+    let mut cmd = cargo_bin_cmd!("c2rust-test");
+    cmd.current_dir(temp_dir.path())
+        .env("C2RUST_CONFIG", &mock_config)
+        .args(["test", "--feature", "fa", "--", "sh", "-c"])
+        .arg("mkdir -p .c2rust/fb/test-logs && echo partial > .c2rust/fb/test-logs/other.log");
+    cmd.assert().success();
+
+    let tree = repo.head().unwrap().peel_to_commit().unwrap().tree().unwrap();
+    let fa_logs = tree.get_path(std::path::Path::new("fa/test-logs")).unwrap().to_object(&repo).unwrap();
+    assert_eq!(fa_logs.as_tree().unwrap().len(), 1);
+    assert!(tree.get_path(std::path::Path::new("mock-config/fa/test.cmd")).is_ok());
+    assert!(tree.get_path(std::path::Path::new("fb")).is_err());
+}